@@ -1,4 +1,7 @@
-use ndarray::{ArrayView, ArrayView2, Array2};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap};
+
+use ndarray::{Array1, ArrayView, ArrayView1, ArrayView2, Array2};
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
@@ -10,7 +13,15 @@ macro_rules! log {
 }
 
 mod utils {
+    // Called at the top of every export. Builds without the
+    // `per_call_panic_hook` feature skip it and rely on the exported
+    // `init_panic_hook()` instead.
     pub fn set_panic_hook() {
+        #[cfg(feature = "per_call_panic_hook")]
+        init_panic_hook();
+    }
+
+    pub fn init_panic_hook() {
         // When the `console_error_panic_hook` feature is enabled, we can call the
         // `set_panic_hook` function at least once during initialization, and then
         // we will get better error messages if our code ever panics.
@@ -22,11 +33,248 @@ mod utils {
     }
 }
 
+/// Installs the panic hook once. Call this at startup when building without
+/// the default `per_call_panic_hook` feature.
+#[wasm_bindgen]
+pub fn init_panic_hook() {
+    utils::init_panic_hook();
+}
+
+mod rng {
+    /// SplitMix64 generator. Small, seedable and good enough for posterior
+    /// sampling; we don't need cryptographic quality here.
+    pub struct SplitMix64 {
+        state: u64,
+    }
+
+    impl SplitMix64 {
+        pub fn new(seed: u64) -> Self {
+            SplitMix64 { state: seed }
+        }
+
+        pub fn next_u64(&mut self) -> u64 {
+            self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z = self.state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^ (z >> 31)
+        }
+
+        /// Uniform sample in (0, 1].
+        pub fn next_f64(&mut self) -> f64 {
+            ((self.next_u64() >> 11) as f64 + 1.0) / (1u64 << 53) as f64
+        }
+
+        /// Standard normal sample via Box-Muller.
+        pub fn next_gaussian(&mut self) -> f64 {
+            let u1 = self.next_f64();
+            let u2 = self.next_f64();
+            (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+        }
+    }
+}
+
+mod simd {
+    // Inner loops for the similarity functions. With the `simd` feature and a
+    // simd128 build these use two-lane f64 vectors; otherwise plain scalar
+    // loops. Lane-wise accumulation reorders the sum, so the two paths can
+    // differ in the last few bits.
+
+    /// Returns `(a · b, b · b)`; callers only read `min(a.len(), b.len())` items.
+    #[cfg(all(feature = "simd", target_arch = "wasm32", target_feature = "simd128"))]
+    pub fn dot_and_norm_sq(a: &[f64], b: &[f64]) -> (f64, f64) {
+        use core::arch::wasm32::*;
+
+        let n = a.len().min(b.len());
+        let mut dot = f64x2_splat(0.0);
+        let mut norm = f64x2_splat(0.0);
+
+        let mut i = 0;
+        while i + 2 <= n {
+            // SAFETY: i + 1 < n for both slices, and v128_load has no alignment requirement
+            let (va, vb) = unsafe {
+                (
+                    v128_load(a.as_ptr().add(i) as *const v128),
+                    v128_load(b.as_ptr().add(i) as *const v128),
+                )
+            };
+            dot = f64x2_add(dot, f64x2_mul(va, vb));
+            norm = f64x2_add(norm, f64x2_mul(vb, vb));
+            i += 2;
+        }
+
+        let mut dot_sum = f64x2_extract_lane::<0>(dot) + f64x2_extract_lane::<1>(dot);
+        let mut norm_sum = f64x2_extract_lane::<0>(norm) + f64x2_extract_lane::<1>(norm);
+        if i < n {
+            dot_sum += a[i] * b[i];
+            norm_sum += b[i] * b[i];
+        }
+
+        (dot_sum, norm_sum)
+    }
+
+    #[cfg(not(all(feature = "simd", target_arch = "wasm32", target_feature = "simd128")))]
+    pub fn dot_and_norm_sq(a: &[f64], b: &[f64]) -> (f64, f64) {
+        let mut dot = 0.0;
+        let mut norm = 0.0;
+        for (&x, &y) in a.iter().zip(b.iter()) {
+            dot += x * y;
+            norm += y * y;
+        }
+        (dot, norm)
+    }
+
+    #[cfg(all(feature = "simd", target_arch = "wasm32", target_feature = "simd128"))]
+    pub fn dot(a: &[f64], b: &[f64]) -> f64 {
+        use core::arch::wasm32::*;
+
+        let n = a.len().min(b.len());
+        let mut acc = f64x2_splat(0.0);
+
+        let mut i = 0;
+        while i + 2 <= n {
+            // SAFETY: i + 1 < n for both slices, and v128_load has no alignment requirement
+            let (va, vb) = unsafe {
+                (
+                    v128_load(a.as_ptr().add(i) as *const v128),
+                    v128_load(b.as_ptr().add(i) as *const v128),
+                )
+            };
+            acc = f64x2_add(acc, f64x2_mul(va, vb));
+            i += 2;
+        }
+
+        let mut sum = f64x2_extract_lane::<0>(acc) + f64x2_extract_lane::<1>(acc);
+        if i < n {
+            sum += a[i] * b[i];
+        }
+        sum
+    }
+
+    #[cfg(not(all(feature = "simd", target_arch = "wasm32", target_feature = "simd128")))]
+    pub fn dot(a: &[f64], b: &[f64]) -> f64 {
+        a.iter().zip(b.iter()).map(|(&x, &y)| x * y).sum()
+    }
+}
+
+mod base64 {
+    // Standard alphabet (RFC 4648) with `=` padding.
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    pub fn encode(bytes: &[u8]) -> String {
+        let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+        for chunk in bytes.chunks(3) {
+            let b0 = chunk[0] as u32;
+            let b1 = chunk.get(1).copied().unwrap_or(0) as u32;
+            let b2 = chunk.get(2).copied().unwrap_or(0) as u32;
+            let n = (b0 << 16) | (b1 << 8) | b2;
+
+            out.push(ALPHABET[(n >> 18) as usize & 63] as char);
+            out.push(ALPHABET[(n >> 12) as usize & 63] as char);
+            out.push(if chunk.len() > 1 { ALPHABET[(n >> 6) as usize & 63] as char } else { '=' });
+            out.push(if chunk.len() > 2 { ALPHABET[n as usize & 63] as char } else { '=' });
+        }
+        out
+    }
+
+    fn decode_char(c: u8) -> Option<u32> {
+        match c {
+            b'A'..=b'Z' => Some((c - b'A') as u32),
+            b'a'..=b'z' => Some((c - b'a') as u32 + 26),
+            b'0'..=b'9' => Some((c - b'0') as u32 + 52),
+            b'+' => Some(62),
+            b'/' => Some(63),
+            _ => None,
+        }
+    }
+
+    pub fn decode(s: &str) -> Result<Vec<u8>, String> {
+        let input = s.trim().as_bytes();
+        if !input.len().is_multiple_of(4) {
+            return Err("Invalid base64: length must be a multiple of 4.".to_string());
+        }
+
+        let mut out = Vec::with_capacity(input.len() / 4 * 3);
+        let chunk_count = input.len() / 4;
+        for (i, chunk) in input.chunks(4).enumerate() {
+            let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+            if padding > 2 || (padding > 0 && i + 1 != chunk_count) {
+                return Err("Invalid base64: unexpected padding.".to_string());
+            }
+
+            let mut n: u32 = 0;
+            for &c in &chunk[..4 - padding] {
+                let v = decode_char(c).ok_or_else(|| format!("Invalid base64 character '{}'.", c as char))?;
+                n = (n << 6) | v;
+            }
+            n <<= 6 * padding as u32;
+
+            out.push((n >> 16) as u8);
+            if padding < 2 {
+                out.push((n >> 8) as u8);
+            }
+            if padding < 1 {
+                out.push(n as u8);
+            }
+        }
+        Ok(out)
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct BanditModel {
     pub a_inv: Vec<f64>, // Flattened d x d matrix
     pub b: Vec<f64>,     // d x 1 vector
     pub dimension: usize,
+    // Shared-feature part for hybrid LinUCB; absent for plain (disjoint) models
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shared: Option<SharedComponent>,
+    // Running CTR counters maintained by `record_impression`; absent on older models
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub impressions: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clicks: Option<u64>,
+    // Number of rank-1 updates applied; set by `create_bandit_model`, absent on older models
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update_count: Option<u64>,
+    // Per-objective reward vectors for multi-objective models; absent for single-objective ones
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub multi: Option<MultiObjective>,
+}
+
+// m reward vectors sharing the model's A_inv, one column per objective.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MultiObjective {
+    pub b: Vec<f64>, // Flattened d x m matrix
+    pub objectives: usize,
+}
+
+impl BanditModel {
+    // clicks / impressions, or None when no impressions have been recorded.
+    fn stored_ctr(&self) -> Option<f64> {
+        match self.impressions {
+            Some(impressions) if impressions > 0 => {
+                Some(self.clicks.unwrap_or(0) as f64 / impressions as f64)
+            }
+            _ => None,
+        }
+    }
+
+    // Advances `update_count` for models that track it; legacy models stay untracked.
+    fn record_updates(&mut self, n: usize) {
+        if let Some(count) = self.update_count {
+            self.update_count = Some(count.saturating_add(n as u64));
+        }
+    }
+}
+
+// Hybrid LinUCB (Li et al. 2010) state for k shared features.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SharedComponent {
+    pub a0_inv: Vec<f64>,  // Flattened k x k matrix
+    pub b0: Vec<f64>,      // k x 1 vector
+    pub b_cross: Vec<f64>, // Flattened d x k matrix B coupling arm and shared features
+    pub dimension: usize,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -43,275 +291,5015 @@ pub struct UcbResult {
     pub ucb: f64,
 }
 
-#[wasm_bindgen]
-pub fn get_ucb_values_bulk(
-    model_js: JsValue,
-    articles_js: JsValue,
-    user_ctr: f64,
-) -> Result<JsValue, JsValue> {
-    utils::set_panic_hook();
+#[derive(Serialize, Deserialize)]
+pub struct UcbDetailedResult {
+    #[serde(rename = "articleId")]
+    pub article_id: String,
+    pub exploitation: f64,
+    pub exploration: f64,
+    pub ucb: f64,
+}
 
-    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
-        .map_err(|e| JsValue::from_str(&e.to_string()))?;
-    let articles: Vec<Article> = serde_wasm_bindgen::from_value(articles_js)
-        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+// alpha * sqrt(x^T A_inv x). A negative variance means A_inv is no longer PSD;
+// strict mode surfaces it, otherwise abs() keeps scoring resilient.
+fn exploration_term(variance: f64, alpha: f64, strict: bool, article_id: &str) -> Result<f64, JsValue> {
+    if strict && variance < 0.0 {
+        return Err(JsValue::from_str(&format!(
+            "Negative predictive variance {} for article {}; A_inv is not positive semi-definite.",
+            variance, article_id
+        )));
+    }
+    Ok(alpha * variance.abs().sqrt())
+}
 
-    let d = model.dimension;
-    if d == 0 {
-        return Err(JsValue::from_str("Bandit model dimension cannot be zero."));
+// Clamps the exploration bonus to `max_exploration` when one is set.
+fn cap_exploration(term2: f64, max_exploration: Option<f64>) -> f64 {
+    match max_exploration {
+        Some(max) => term2.min(max),
+        None => term2,
     }
+}
 
-    let a_inv = ArrayView2::from_shape((d, d), &model.a_inv)
-        .map_err(|e| JsValue::from_str(&e.to_string()))?;
-    let b = ArrayView::from(&model.b);
+// Per-call LinUCB state: hat_theta is computed once and reused for every article.
+struct UcbScorer<'a> {
+    a_inv: ArrayView2<'a, f64>,
+    hat_theta: Array1<f64>,
+    alpha: f64,
+    strict: bool,
+    normalize: bool,
+    max_exploration: Option<f64>,
+}
 
-    // Dynamically adjust alpha based on user CTR
-    let base_alpha = 0.5;
-    let alpha = base_alpha + (1.0 - user_ctr) * 0.5;
+impl<'a> UcbScorer<'a> {
+    fn new(model: &'a BanditModel, user_ctr: f64, options: &UcbOptions) -> Result<Self, JsValue> {
+        // A malformed model from JS would otherwise panic inside ndarray's dot()
+        validate_bandit_model(model).map_err(|e| JsValue::from_str(&e))?;
 
-    let hat_theta = a_inv.dot(&b);
+        let d = model.dimension;
 
-    let mut ucb_results: Vec<UcbResult> = Vec::with_capacity(articles.len());
+        let a_inv = ArrayView2::from_shape((d, d), &model.a_inv)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let b = ArrayView::from(&model.b);
 
-    for article in articles {
-        if article.embedding.len() != d {
+        let hat_theta = a_inv.dot(&b);
+
+        Ok(UcbScorer {
+            a_inv,
+            hat_theta,
+            alpha: resolve_alpha(model, user_ctr, options),
+            strict: options.strict,
+            normalize: options.normalize,
+            max_exploration: options.max_exploration,
+        })
+    }
+
+    // Exploitation (x·hat_theta) and exploration (alpha * sqrt(x^T A_inv x)),
+    // or None when the embedding dimension doesn't match the model.
+    fn score(&self, article_id: &str, embedding: &[f64]) -> Result<Option<(f64, f64)>, JsValue> {
+        if embedding.len() != self.hat_theta.len() {
             // Skip articles with mismatched embedding dimensions
-            log!("Skipping article {} due to embedding dimension mismatch.", article.article_id);
-            continue;
+            log!("Skipping article {} due to embedding dimension mismatch.", article_id);
+            return Ok(None);
         }
-        let x = ArrayView::from(&article.embedding);
+        let x = ArrayView::from(embedding);
 
-        let term1 = x.dot(&hat_theta);
+        let term1 = x.dot(&self.hat_theta);
         
         // x_T_A_inv = x^T * A_inv
-        let x_t_a_inv = x.dot(&a_inv);
+        let x_t_a_inv = x.dot(&self.a_inv);
         
         // term2_sqrt = x^T * A_inv * x
         let term2_sqrt = x_t_a_inv.dot(&x);
-        
-        let term2 = alpha * term2_sqrt.abs().sqrt();
-
-        ucb_results.push(UcbResult {
-            article_id: article.article_id,
-            ucb: term1 + term2,
-        });
-    }
-
-    Ok(serde_wasm_bindgen::to_value(&ucb_results)?)
-}
-
-#[wasm_bindgen]
-pub fn update_bandit_model(
-    model_js: JsValue,
-    embedding: &[f64],
-    reward: f64,
-) -> Result<JsValue, JsValue> {
-    utils::set_panic_hook();
 
-    let mut model: BanditModel = serde_wasm_bindgen::from_value(model_js)
-        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize BanditModel: {}", e)))?;
+        let term2 = exploration_term(term2_sqrt, self.alpha, self.strict, article_id)?;
 
-    let d = model.dimension;
+        // Both terms scale linearly with ||x||, so dividing them equals scoring x / ||x||
+        let (term1, term2) = if self.normalize {
+            let norm = magnitude(embedding);
+            if norm == 0.0 {
+                return Ok(Some((0.0, 0.0)));
+            }
+            (term1 / norm, term2 / norm)
+        } else {
+            (term1, term2)
+        };
 
-    // 1) reject zero-dimension early
-    if d == 0 {
-        return Err(JsValue::from_str("Bandit model dimension cannot be zero."));
+        Ok(Some((term1, cap_exploration(term2, self.max_exploration))))
     }
+}
 
-    // 2) basic length checks for a_inv and b
-    if model.a_inv.len() != d * d {
-        return Err(JsValue::from_str(&format!(
-            "Bandit model A_inv length mismatch: expected {}, got {}",
-            d * d,
-            model.a_inv.len()
-        )));
+// Shared LinUCB scoring. Returns exploitation and exploration separately so
+// callers can pick what they expose.
+fn compute_ucb_detailed(
+    model: &BanditModel,
+    articles: Vec<Article>,
+    user_ctr: f64,
+    options: &UcbOptions,
+) -> Result<Vec<UcbDetailedResult>, JsValue> {
+    // No articles is always an empty result, even for an invalid model
+    if articles.is_empty() {
+        return Ok(Vec::new());
     }
 
-    if model.b.len() != d {
-        return Err(JsValue::from_str(&format!(
-            "Bandit model b length mismatch: expected {}, got {}",
-            d,
-            model.b.len()
-        )));
-    }
+    let scorer = UcbScorer::new(model, user_ctr, options)?;
 
-    // 3) check finite elements in a_inv and b and embedding
-    if model.a_inv.iter().any(|&v| !v.is_finite()) {
-        return Err(JsValue::from_str("Bandit model A_inv contains non-finite values (NaN/Inf)."));
-    }
-    if model.b.iter().any(|&v| !v.is_finite()) {
-        return Err(JsValue::from_str("Bandit model b contains non-finite values (NaN/Inf)."));
-    }
-    if embedding.iter().any(|&v| !v.is_finite()) {
-        return Err(JsValue::from_str("Embedding contains non-finite values (NaN/Inf)."));
-    }
+    let mut ucb_results: Vec<UcbDetailedResult> = Vec::with_capacity(articles.len());
 
-    if embedding.len() != d {
-        return Err(JsValue::from_str("Embedding dimension mismatch."));
+    for article in articles {
+        if let Some((term1, term2)) = scorer.score(&article.article_id, &article.embedding)? {
+            ucb_results.push(UcbDetailedResult {
+                article_id: article.article_id,
+                exploitation: term1,
+                exploration: term2,
+                ucb: term1 + term2,
+            });
+        }
     }
 
-    let x = ArrayView::from(embedding);
-    let mut a_inv = Array2::from_shape_vec((d, d), model.a_inv)
-        .map_err(|e| JsValue::from_str(&format!("A_inv shape error: {}", e)))?;
-    let mut b = ArrayView::from(&model.b).to_owned();
-
-    // Sherman-Morrison computation
-    let a_inv_x = a_inv.dot(&x);
-    let x_t_a_inv_x = x.dot(&a_inv_x);
-    let denominator = 1.0 + x_t_a_inv_x;
-
-    // 4) robust near-zero check
-    const EPS: f64 = 1e-12;
-    if !denominator.is_finite() {
-        return Err(JsValue::from_str("Denominator is non-finite (NaN/Inf) in Sherman-Morrison update."));
-    }
-    if denominator.abs() < EPS {
-        return Err(JsValue::from_str("Denominator too small in Sherman-Morrison update (numerical instability)."));
-    }
+    Ok(ucb_results)
+}
 
-    // 5) compute numerator safely (shapes already validated)
-    let numerator_matrix = a_inv_x
-        .insert_axis(ndarray::Axis(1))
-        .dot(&x.insert_axis(ndarray::Axis(0)).dot(&a_inv));
+#[derive(Serialize, Deserialize, Default)]
+pub struct UcbOptions {
+    #[serde(rename = "baseAlpha", default)]
+    pub base_alpha: Option<f64>,
+    #[serde(rename = "alphaOverride", default)]
+    pub alpha_override: Option<f64>,
+    // Error on negative x^T A_inv x instead of taking abs()
+    #[serde(default)]
+    pub strict: bool,
+    // Use the model's recorded clicks / impressions instead of `user_ctr` when available
+    #[serde(rename = "useStoredCtr", default)]
+    pub use_stored_ctr: bool,
+    // Score each embedding as x / ||x||; zero embeddings score 0
+    #[serde(default)]
+    pub normalize: bool,
+    // Upper bound on the exploration bonus, so rare directions can't dominate
+    #[serde(rename = "maxExploration", default)]
+    pub max_exploration: Option<f64>,
+}
 
-    // 6) subtract, then update b
-    a_inv = a_inv - numerator_matrix / denominator;
+const DEFAULT_BASE_ALPHA: f64 = 0.5;
 
-    let x_scaled = x.to_owned() * reward;
-    // ensure shapes match before addition
-    if b.len() != x_scaled.len() {
-        return Err(JsValue::from_str("Shape mismatch when updating b."));
+// null/undefined options fall back to the defaults so existing callers keep working.
+fn parse_ucb_options(options_js: Option<JsValue>) -> Result<UcbOptions, JsValue> {
+    let options_js = match options_js {
+        Some(js) if !js.is_null() && !js.is_undefined() => js,
+        _ => return Ok(UcbOptions::default()),
+    };
+    let options: UcbOptions = serde_wasm_bindgen::from_value(options_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize UCB options: {}", e)))?;
+    if let Some(max) = options.max_exploration {
+        if max.is_nan() || max < 0.0 {
+            return Err(JsValue::from_str("maxExploration must be non-negative."));
+        }
     }
-    b = b + x_scaled;
-
-    // finalize
-    model.a_inv = a_inv.into_raw_vec_and_offset().0;
-    model.b = b.into_raw_vec_and_offset().0;
+    Ok(options)
+}
 
-    Ok(serde_wasm_bindgen::to_value(&model).map_err(|e| JsValue::from_str(&e.to_string()))?)
+// Dynamically adjust alpha based on user CTR, unless an explicit override is given
+fn resolve_alpha(model: &BanditModel, user_ctr: f64, options: &UcbOptions) -> f64 {
+    if let Some(alpha) = options.alpha_override {
+        return alpha;
+    }
+    let user_ctr = match model.stored_ctr() {
+        Some(ctr) if options.use_stored_ctr => ctr,
+        _ => user_ctr,
+    };
+    let base_alpha = options.base_alpha.unwrap_or(DEFAULT_BASE_ALPHA);
+    base_alpha + (1.0 - user_ctr) * 0.5
 }
 
 #[wasm_bindgen]
-pub fn cosine_similarity(
-    vec1_js: JsValue,
-    vec2_js: JsValue,
-) -> Result<f64, JsValue> {
+pub fn get_ucb_values_bulk(
+    model_js: JsValue,
+    articles_js: JsValue,
+    user_ctr: f64,
+    options_js: Option<JsValue>,
+) -> Result<JsValue, JsValue> {
     utils::set_panic_hook();
 
-    let vec1: Vec<f64> = serde_wasm_bindgen::from_value(vec1_js)
-        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vec1: {}", e)))?;
-    let vec2: Vec<f64> = serde_wasm_bindgen::from_value(vec2_js)
-        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vec2: {}", e)))?;
-
-    if vec1.len() != vec2.len() {
-        return Err(JsValue::from_str("Vector dimensions mismatch."));
-    }
-    if vec1.is_empty() {
-        return Err(JsValue::from_str("Vectors cannot be empty."));
-    }
-
-    let dot_product: f64 = vec1.iter().zip(vec2.iter()).map(|(&a, &b)| a * b).sum();
-    let magnitude1: f64 = vec1.iter().map(|&a| a * a).sum::<f64>().sqrt();
-    let magnitude2: f64 = vec2.iter().map(|&b| b * b).sum::<f64>().sqrt();
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let articles: Vec<Article> = serde_wasm_bindgen::from_value(articles_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let options = parse_ucb_options(options_js)?;
 
-    if magnitude1 == 0.0 || magnitude2 == 0.0 {
-        return Ok(0.0); // Avoid division by zero, return 0 similarity for zero vectors
-    }
+    let ucb_results: Vec<UcbResult> = compute_ucb_detailed(&model, articles, user_ctr, &options)?
+        .into_iter()
+        .map(|r| UcbResult { article_id: r.article_id, ucb: r.ucb })
+        .collect();
 
-    Ok(dot_product / (magnitude1 * magnitude2))
+    Ok(serde_wasm_bindgen::to_value(&ucb_results)?)
 }
 
+/// Scores one article with exactly the alpha and math of
+/// `get_ucb_values_bulk`, for articles that arrive after the bulk call. Unlike
+/// the bulk variants, a dimension mismatch is an error instead of a skip.
 #[wasm_bindgen]
-pub fn cosine_similarity_bulk(
-    vec1s_js: JsValue,
-    vec2s_js: JsValue,
+pub fn score_single_article(
+    model_js: JsValue,
+    embedding: &[f64],
+    article_id: &str,
+    user_ctr: f64,
 ) -> Result<JsValue, JsValue> {
     utils::set_panic_hook();
 
-    let vec1s: Vec<Vec<f64>> = serde_wasm_bindgen::from_value(vec1s_js)
-        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vec1s: {}", e)))?;
-    let vec2s: Vec<Vec<f64>> = serde_wasm_bindgen::from_value(vec2s_js)
-        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vec2s: {}", e)))?;
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
 
-    if vec1s.len() != vec2s.len() {
-        return Err(JsValue::from_str("Input vector arrays must have the same length."));
+    if embedding.len() != model.dimension {
+        return Err(JsValue::from_str(&format!(
+            "Article {} embedding dimension mismatch: expected {}, got {}",
+            article_id,
+            model.dimension,
+            embedding.len()
+        )));
     }
 
-    let mut results = Vec::with_capacity(vec1s.len());
+    let scorer = UcbScorer::new(&model, user_ctr, &UcbOptions::default())?;
+    // The dimension is checked above, so score() never skips here
+    let (term1, term2) = scorer.score(article_id, embedding)?.unwrap_or_default();
 
-    for i in 0..vec1s.len() {
-        let vec1 = &vec1s[i];
-        let vec2 = &vec2s[i];
+    let result = UcbResult { article_id: article_id.to_string(), ucb: term1 + term2 };
 
-        if vec1.len() != vec2.len() {
-            results.push(0.0); // 次元が異なる場合は類似度0
-            continue;
-        }
-        if vec1.is_empty() {
-            results.push(0.0); // 空のベクトルの場合は類似度0
-            continue;
-        }
+    Ok(serde_wasm_bindgen::to_value(&result)?)
+}
 
-        let dot_product: f64 = vec1.iter().zip(vec2.iter()).map(|(&a, &b)| a * b).sum();
-        let magnitude1: f64 = vec1.iter().map(|&a| a * a).sum::<f64>().sqrt();
-        let magnitude2: f64 = vec2.iter().map(|&b| b * b).sum::<f64>().sqrt();
+/// `get_ucb_values_bulk` as two parallel arrays: `article_ids[i]` scored
+/// `ucb_values[i]`. `ucb_values` is handed to JS as a `Float64Array`, so no
+/// per-article object is allocated.
+#[wasm_bindgen]
+pub struct UcbArrays {
+    article_ids: Vec<String>,
+    ucb_values: Vec<f64>,
+}
 
-        if magnitude1 == 0.0 || magnitude2 == 0.0 {
-            results.push(0.0); // ゼロベクトルの場合は類似度0
-        } else {
-            results.push(dot_product / (magnitude1 * magnitude2));
-        }
+#[wasm_bindgen]
+impl UcbArrays {
+    #[wasm_bindgen(getter)]
+    pub fn article_ids(&self) -> Vec<String> {
+        self.article_ids.clone()
     }
 
-    Ok(serde_wasm_bindgen::to_value(&results)?)
+    #[wasm_bindgen(getter)]
+    pub fn ucb_values(&self) -> Vec<f64> {
+        self.ucb_values.clone()
+    }
 }
 
 #[wasm_bindgen]
-pub fn calculate_similarity_matrix(
-    vectors_js: JsValue,
-) -> Result<JsValue, JsValue> {
+pub fn get_ucb_values_bulk_arrays(
+    model_js: JsValue,
+    articles_js: JsValue,
+    user_ctr: f64,
+) -> Result<UcbArrays, JsValue> {
     utils::set_panic_hook();
 
-    let vectors: Vec<Vec<f64>> = serde_wasm_bindgen::from_value(vectors_js)
-        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vectors: {}", e)))?;
-
-    let n = vectors.len();
-    let mut similarity_matrix = vec![vec![0.0; n]; n];
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let articles: Vec<Article> = serde_wasm_bindgen::from_value(articles_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let results = compute_ucb_detailed(&model, articles, user_ctr, &UcbOptions::default())?;
+    let mut arrays = UcbArrays {
+        article_ids: Vec::with_capacity(results.len()),
+        ucb_values: Vec::with_capacity(results.len()),
+    };
+    for r in results {
+        arrays.article_ids.push(r.article_id);
+        arrays.ucb_values.push(r.ucb);
+    }
+
+    Ok(arrays)
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ArticleF32 {
+    #[serde(rename = "articleId")]
+    pub article_id: String,
+    pub embedding: Vec<f32>,
+}
+
+/// `get_ucb_values_bulk` for f32 embeddings. Embeddings are promoted to f64
+/// before scoring and the model stays f64, so the only precision lost is
+/// whatever was already lost producing the f32 embedding (~7 significant
+/// digits), which is well below the noise in UCB scores.
+#[wasm_bindgen]
+pub fn get_ucb_values_bulk_f32(
+    model_js: JsValue,
+    articles_js: JsValue,
+    user_ctr: f64,
+    options_js: Option<JsValue>,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let articles: Vec<ArticleF32> = serde_wasm_bindgen::from_value(articles_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let options = parse_ucb_options(options_js)?;
+
+    let articles: Vec<Article> = articles
+        .into_iter()
+        .map(|a| Article {
+            article_id: a.article_id,
+            embedding: a.embedding.into_iter().map(f64::from).collect(),
+        })
+        .collect();
+
+    // Mismatched dimensions are skipped (and logged) inside compute_ucb_detailed
+    let ucb_results: Vec<UcbResult> = compute_ucb_detailed(&model, articles, user_ctr, &options)?
+        .into_iter()
+        .map(|r| UcbResult { article_id: r.article_id, ucb: r.ucb })
+        .collect();
+
+    Ok(serde_wasm_bindgen::to_value(&ucb_results)?)
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SparseArticle {
+    #[serde(rename = "articleId")]
+    pub article_id: String,
+    pub indices: Vec<usize>,
+    pub values: Vec<f64>,
+}
+
+// (index, value) pairs sorted by index, with the values of repeated indices
+// summed, so the norm matches the dense vector the entries describe.
+fn merge_sparse_entries(indices: &[usize], values: &[f64]) -> Vec<(usize, f64)> {
+    let mut entries: Vec<(usize, f64)> = indices.iter().copied().zip(values.iter().copied()).collect();
+    entries.sort_by_key(|&(i, _)| i);
+    entries.dedup_by(|next, kept| {
+        if next.0 == kept.0 {
+            kept.1 += next.1;
+            true
+        } else {
+            false
+        }
+    });
+    entries
+}
+
+/// `get_ucb_values_bulk` for sparse embeddings given as parallel
+/// `indices`/`values` arrays. Only nonzero entries are visited, so the cost per
+/// article is O(nnz^2) instead of O(d^2).
+#[wasm_bindgen]
+pub fn get_ucb_values_bulk_sparse(
+    model_js: JsValue,
+    articles_js: JsValue,
+    user_ctr: f64,
+    options_js: Option<JsValue>,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let articles: Vec<SparseArticle> = serde_wasm_bindgen::from_value(articles_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let options = parse_ucb_options(options_js)?;
+
+    validate_bandit_model(&model).map_err(|e| JsValue::from_str(&e))?;
+
+    let d = model.dimension;
+
+    let a_inv = ArrayView2::from_shape((d, d), &model.a_inv)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let b = ArrayView::from(&model.b);
+
+    let alpha = resolve_alpha(&model, user_ctr, &options);
+    let hat_theta = a_inv.dot(&b);
+
+    let mut ucb_results: Vec<UcbResult> = Vec::with_capacity(articles.len());
+
+    for article in articles {
+        if article.indices.len() != article.values.len() {
+            return Err(JsValue::from_str(&format!(
+                "Sparse article {} has {} indices but {} values.",
+                article.article_id,
+                article.indices.len(),
+                article.values.len()
+            )));
+        }
+        if let Some(&bad) = article.indices.iter().find(|&&i| i >= d) {
+            return Err(JsValue::from_str(&format!(
+                "Sparse article {} has index {} out of range for dimension {}.",
+                article.article_id, bad, d
+            )));
+        }
+
+        let entries = merge_sparse_entries(&article.indices, &article.values);
+
+        let term1: f64 = entries.iter().map(|&(i, v)| v * hat_theta[i]).sum();
+
+        // x^T A_inv x over the nonzero entries only
+        let mut term2_sqrt = 0.0;
+        for &(i, vi) in &entries {
+            for &(j, vj) in &entries {
+                term2_sqrt += vi * a_inv[[i, j]] * vj;
+            }
+        }
+
+        let term2 = exploration_term(term2_sqrt, alpha, options.strict, &article.article_id)?;
+
+        let (term1, term2) = if options.normalize {
+            let norm = entries.iter().map(|&(_, v)| v * v).sum::<f64>().sqrt();
+            if norm == 0.0 { (0.0, 0.0) } else { (term1 / norm, term2 / norm) }
+        } else {
+            (term1, term2)
+        };
+
+        ucb_results.push(UcbResult {
+            article_id: article.article_id,
+            ucb: term1 + cap_exploration(term2, options.max_exploration),
+        });
+    }
+
+    Ok(serde_wasm_bindgen::to_value(&ucb_results)?)
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct HybridArticle {
+    #[serde(rename = "articleId")]
+    pub article_id: String,
+    pub embedding: Vec<f64>,
+    #[serde(rename = "sharedFeatures", default)]
+    pub shared_features: Vec<f64>,
+}
+
+fn validate_shared_component(shared: &SharedComponent, d: usize) -> Result<(), String> {
+    let k = shared.dimension;
+    if k == 0 {
+        return Err("Shared component dimension cannot be zero.".to_string());
+    }
+    if shared.a0_inv.len() != k * k {
+        return Err(format!(
+            "Shared A0_inv length mismatch: expected {}, got {}",
+            k * k,
+            shared.a0_inv.len()
+        ));
+    }
+    if shared.b0.len() != k {
+        return Err(format!("Shared b0 length mismatch: expected {}, got {}", k, shared.b0.len()));
+    }
+    if shared.b_cross.len() != d * k {
+        return Err(format!(
+            "Shared B length mismatch: expected {}, got {}",
+            d * k,
+            shared.b_cross.len()
+        ));
+    }
+    if shared.a0_inv.iter().chain(&shared.b0).chain(&shared.b_cross).any(|v| !v.is_finite()) {
+        return Err("Shared component contains non-finite values (NaN/Inf).".to_string());
+    }
+    Ok(())
+}
+
+/// Hybrid LinUCB scoring (Li et al. 2010, Algorithm 2). Each article carries
+/// its arm features (`embedding`, x) and `sharedFeatures` (z):
+///
+/// ```text
+/// beta  = A0_inv b0
+/// theta = A_inv (b - B beta)
+/// s     = z'A0_inv z - 2 z'A0_inv B' A_inv x + x'A_inv x + x'A_inv B A0_inv B' A_inv x
+/// ucb   = z'beta + x'theta + alpha * sqrt(s)
+/// ```
+///
+/// Models without a shared component fall back to plain disjoint LinUCB and
+/// `sharedFeatures` is ignored.
+#[wasm_bindgen]
+pub fn get_ucb_values_hybrid(
+    model_js: JsValue,
+    articles_js: JsValue,
+    user_ctr: f64,
+    options_js: Option<JsValue>,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let articles: Vec<HybridArticle> = serde_wasm_bindgen::from_value(articles_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let options = parse_ucb_options(options_js)?;
+
+    let shared = match &model.shared {
+        Some(shared) => shared,
+        None => {
+            let articles: Vec<Article> = articles
+                .into_iter()
+                .map(|a| Article { article_id: a.article_id, embedding: a.embedding })
+                .collect();
+            let ucb_results: Vec<UcbResult> = compute_ucb_detailed(&model, articles, user_ctr, &options)?
+                .into_iter()
+                .map(|r| UcbResult { article_id: r.article_id, ucb: r.ucb })
+                .collect();
+            return Ok(serde_wasm_bindgen::to_value(&ucb_results)?);
+        }
+    };
+
+    if articles.is_empty() {
+        return Ok(serde_wasm_bindgen::to_value(&Vec::<UcbResult>::new())?);
+    }
+
+    validate_bandit_model(&model).map_err(|e| JsValue::from_str(&e))?;
+
+    let d = model.dimension;
+    validate_shared_component(shared, d).map_err(|e| JsValue::from_str(&e))?;
+    let k = shared.dimension;
+
+    let a_inv = ArrayView2::from_shape((d, d), &model.a_inv)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let b = ArrayView::from(&model.b);
+    let a0_inv = ArrayView2::from_shape((k, k), &shared.a0_inv)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let b0 = ArrayView::from(&shared.b0);
+    let b_cross = ArrayView2::from_shape((d, k), &shared.b_cross)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let alpha = resolve_alpha(&model, user_ctr, &options);
+
+    let beta = a0_inv.dot(&b0);
+    let theta = a_inv.dot(&(&b - &b_cross.dot(&beta)));
+
+    let mut ucb_results: Vec<UcbResult> = Vec::with_capacity(articles.len());
+
+    for mut article in articles {
+        if article.embedding.len() != d || article.shared_features.len() != k {
+            log!("Skipping article {} due to embedding dimension mismatch.", article.article_id);
+            continue;
+        }
+        if options.normalize {
+            l2_normalize(&mut article.embedding);
+        }
+        let x = ArrayView::from(&article.embedding);
+        let z = ArrayView::from(&article.shared_features);
+
+        let a_inv_x = a_inv.dot(&x);
+        let bt_a_inv_x = b_cross.t().dot(&a_inv_x);
+        let a0_inv_z = a0_inv.dot(&z);
+
+        let s = z.dot(&a0_inv_z) - 2.0 * a0_inv_z.dot(&bt_a_inv_x)
+            + x.dot(&a_inv_x)
+            + bt_a_inv_x.dot(&a0_inv.dot(&bt_a_inv_x));
+
+        let exploitation = z.dot(&beta) + x.dot(&theta);
+        let exploration = cap_exploration(
+            exploration_term(s, alpha, options.strict, &article.article_id)?,
+            options.max_exploration,
+        );
+
+        ucb_results.push(UcbResult {
+            article_id: article.article_id,
+            ucb: exploitation + exploration,
+        });
+    }
+
+    Ok(serde_wasm_bindgen::to_value(&ucb_results)?)
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UcbTiming {
+    pub deserialize: f64,
+    pub compute: f64,
+    pub serialize: f64,
+}
+
+// Microsecond clock backed by the global `performance` object, which exists in
+// both browsers and Workers. Falls back to Date.now() if it is missing.
+struct Stopwatch {
+    performance: Option<web_sys::Performance>,
+}
+
+impl Stopwatch {
+    fn new() -> Self {
+        let performance = js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str("performance"))
+            .ok()
+            .and_then(|p| p.dyn_into::<web_sys::Performance>().ok());
+        Stopwatch { performance }
+    }
+
+    fn now_us(&self) -> f64 {
+        let ms = match &self.performance {
+            Some(p) => p.now(),
+            None => js_sys::Date::now(),
+        };
+        ms * 1000.0
+    }
+}
+
+/// `get_ucb_values_bulk` that also reports how long each phase took, as
+/// `{ results, timingUs: { deserialize, compute, serialize } }` in microseconds.
+#[wasm_bindgen]
+pub fn get_ucb_values_bulk_profiled(
+    model_js: JsValue,
+    articles_js: JsValue,
+    user_ctr: f64,
+    options_js: Option<JsValue>,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let clock = Stopwatch::new();
+    let start = clock.now_us();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let articles: Vec<Article> = serde_wasm_bindgen::from_value(articles_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let options = parse_ucb_options(options_js)?;
+    let deserialized = clock.now_us();
+
+    let ucb_results: Vec<UcbResult> = compute_ucb_detailed(&model, articles, user_ctr, &options)?
+        .into_iter()
+        .map(|r| UcbResult { article_id: r.article_id, ucb: r.ucb })
+        .collect();
+    let computed = clock.now_us();
+
+    let results_js = serde_wasm_bindgen::to_value(&ucb_results)?;
+    let serialized = clock.now_us();
+
+    let timing = UcbTiming {
+        deserialize: deserialized - start,
+        compute: computed - deserialized,
+        serialize: serialized - computed,
+    };
+
+    let output = js_sys::Object::new();
+    js_sys::Reflect::set(&output, &JsValue::from_str("results"), &results_js)?;
+    js_sys::Reflect::set(&output, &JsValue::from_str("timingUs"), &serde_wasm_bindgen::to_value(&timing)?)?;
+
+    Ok(output.into())
+}
+
+/// Same scoring as `get_ucb_values_bulk`, but keeps the exploitation and
+/// exploration terms alongside the final UCB value for debugging.
+#[wasm_bindgen]
+pub fn get_ucb_values_detailed(
+    model_js: JsValue,
+    articles_js: JsValue,
+    user_ctr: f64,
+    options_js: Option<JsValue>,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let articles: Vec<Article> = serde_wasm_bindgen::from_value(articles_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let options = parse_ucb_options(options_js)?;
+
+    let results = compute_ucb_detailed(&model, articles, user_ctr, &options)?;
+
+    Ok(serde_wasm_bindgen::to_value(&results)?)
+}
+
+// Ascending score order shared by every ranking API: total_cmp, except that
+// NaN (of either sign) is below every real score, so NaN always ranks last.
+fn score_cmp(a: f64, b: f64) -> Ordering {
+    b.is_nan().cmp(&a.is_nan()).then_with(|| a.total_cmp(&b))
+}
+
+// Heap entry ordered by score (see `score_cmp`), ties broken so that the
+// smaller key ranks higher.
+struct HeapEntry<K: Ord> {
+    score: f64,
+    key: K,
+    index: usize,
+}
+
+impl<K: Ord> PartialEq for HeapEntry<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<K: Ord> Eq for HeapEntry<K> {}
+
+impl<K: Ord> PartialOrd for HeapEntry<K> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord> Ord for HeapEntry<K> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        score_cmp(self.score, other.score).then_with(|| other.key.cmp(&self.key))
+    }
+}
+
+// The k largest entries, highest first, in a single pass. Keeps a min-heap of
+// size k so we never hold or sort the whole candidate list.
+fn top_k_entries<K: Ord>(entries: impl Iterator<Item = HeapEntry<K>>, k: usize) -> Vec<HeapEntry<K>> {
+    if k == 0 {
+        return Vec::new();
+    }
+
+    // Never reserve more than the input can fill; a huge k just means "all of them"
+    let (lower, upper) = entries.size_hint();
+    let mut heap: BinaryHeap<Reverse<HeapEntry<K>>> = BinaryHeap::with_capacity(k.min(upper.unwrap_or(lower)));
+    for entry in entries {
+        if heap.len() < k {
+            heap.push(Reverse(entry));
+        } else if let Some(Reverse(min)) = heap.peek() {
+            if entry > *min {
+                heap.pop();
+                heap.push(Reverse(entry));
+            }
+        }
+    }
+
+    // into_sorted_vec is ascending on Reverse, i.e. descending by score
+    heap.into_sorted_vec().into_iter().map(|Reverse(e)| e).collect()
+}
+
+// Indices of the k largest scores, highest first, with equal scores ordered by
+// ascending key.
+fn top_k_indices<K: Ord>(scores: &[f64], keys: &[K], k: usize) -> Vec<usize> {
+    let entries = scores
+        .iter()
+        .zip(keys.iter())
+        .enumerate()
+        .map(|(index, (&score, key))| HeapEntry { score, key, index });
+
+    top_k_entries(entries, k.min(scores.len())).into_iter().map(|e| e.index).collect()
+}
+
+/// Ties are broken by ascending `articleId`, so the output is deterministic.
+#[wasm_bindgen]
+pub fn get_top_k_ucb(
+    model_js: JsValue,
+    articles_js: JsValue,
+    user_ctr: f64,
+    k: usize,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let articles: Vec<Article> = serde_wasm_bindgen::from_value(articles_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let results = compute_ucb_detailed(&model, articles, user_ctr, &UcbOptions::default())?;
+
+    let scores: Vec<f64> = results.iter().map(|r| r.ucb).collect();
+    let ids: Vec<&str> = results.iter().map(|r| r.article_id.as_str()).collect();
+    let top_indices = top_k_indices(&scores, &ids, k);
+
+    let mut scored: Vec<Option<UcbDetailedResult>> = results.into_iter().map(Some).collect();
+    let top: Vec<UcbResult> = top_indices
+        .into_iter()
+        .filter_map(|i| scored[i].take())
+        .map(|r| UcbResult { article_id: r.article_id, ucb: r.ucb })
+        .collect();
+
+    Ok(serde_wasm_bindgen::to_value(&top)?)
+}
+
+/// `get_ucb_values_bulk` output sorted by descending UCB, with ties broken
+/// by ascending `articleId` as in `get_top_k_ucb`.
+#[wasm_bindgen]
+pub fn get_ucb_values_sorted(
+    model_js: JsValue,
+    articles_js: JsValue,
+    user_ctr: f64,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let articles: Vec<Article> = serde_wasm_bindgen::from_value(articles_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let mut results: Vec<UcbResult> = compute_ucb_detailed(&model, articles, user_ctr, &UcbOptions::default())?
+        .into_iter()
+        .map(|r| UcbResult { article_id: r.article_id, ucb: r.ucb })
+        .collect();
+
+    sort_ucb_results(&mut results);
+
+    Ok(serde_wasm_bindgen::to_value(&results)?)
+}
+
+// Ranking order of UCB results: descending UCB with ties by ascending
+// articleId. NaN scores (e.g. from a NaN embedding) go last, as in `score_cmp`.
+fn rank_ucb(a: &UcbResult, b: &UcbResult) -> Ordering {
+    score_cmp(b.ucb, a.ucb).then_with(|| a.article_id.cmp(&b.article_id))
+}
+
+fn sort_ucb_results(results: &mut [UcbResult]) {
+    results.sort_by(rank_ucb);
+}
+
+/// Epsilon-greedy baseline: each article independently gets a uniform random
+/// score in (0, 1] with probability `epsilon`, and its exploitation score
+/// `x · hat_theta` otherwise. Output has the same shape as `get_ucb_values_bulk`.
+#[wasm_bindgen]
+pub fn score_epsilon_greedy(
+    model_js: JsValue,
+    articles_js: JsValue,
+    epsilon: f64,
+    seed: u64,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let articles: Vec<Article> = serde_wasm_bindgen::from_value(articles_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    if !(0.0..=1.0).contains(&epsilon) {
+        return Err(JsValue::from_str("epsilon must be within [0, 1]."));
+    }
+
+    validate_bandit_model(&model).map_err(|e| JsValue::from_str(&e))?;
+
+    let d = model.dimension;
+
+    let a_inv = ArrayView2::from_shape((d, d), &model.a_inv)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let b = ArrayView::from(&model.b);
+
+    let hat_theta = a_inv.dot(&b);
+    let mut rng = rng::SplitMix64::new(seed);
+
+    let mut results: Vec<UcbResult> = Vec::with_capacity(articles.len());
+
+    for article in articles {
+        if article.embedding.len() != d {
+            log!("Skipping article {} due to embedding dimension mismatch.", article.article_id);
+            continue;
+        }
+
+        let score = if rng.next_f64() <= epsilon {
+            rng.next_f64()
+        } else {
+            ArrayView::from(&article.embedding).dot(&hat_theta)
+        };
+
+        results.push(UcbResult {
+            article_id: article.article_id,
+            ucb: score,
+        });
+    }
+
+    Ok(serde_wasm_bindgen::to_value(&results)?)
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct VarianceResult {
+    #[serde(rename = "articleId")]
+    pub article_id: String,
+    pub variance: f64,
+}
+
+/// Raw predictive variance `x^T A_inv x` per article (the quantity under the
+/// square root in the UCB exploration term), without any abs() applied.
+#[wasm_bindgen]
+pub fn get_prediction_variance_bulk(
+    model_js: JsValue,
+    articles_js: JsValue,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let articles: Vec<Article> = serde_wasm_bindgen::from_value(articles_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    validate_bandit_model(&model).map_err(|e| JsValue::from_str(&e))?;
+
+    let d = model.dimension;
+
+    let a_inv = ArrayView2::from_shape((d, d), &model.a_inv)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let mut results: Vec<VarianceResult> = Vec::with_capacity(articles.len());
+
+    for article in articles {
+        if article.embedding.len() != d {
+            log!("Skipping article {} due to embedding dimension mismatch.", article.article_id);
+            continue;
+        }
+        let x = ArrayView::from(&article.embedding);
+
+        results.push(VarianceResult {
+            article_id: article.article_id,
+            variance: x.dot(&a_inv).dot(&x),
+        });
+    }
+
+    Ok(serde_wasm_bindgen::to_value(&results)?)
+}
+
+/// Returns only the highest-UCB article, found in a single pass. Ties are
+/// broken by ascending `articleId`, matching `get_top_k_ucb`.
+#[wasm_bindgen]
+pub fn get_best_arm(
+    model_js: JsValue,
+    articles_js: JsValue,
+    user_ctr: f64,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let articles: Vec<Article> = serde_wasm_bindgen::from_value(articles_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    if articles.is_empty() {
+        return Err(JsValue::from_str("Cannot select best arm from an empty article list."));
+    }
+
+    let scorer = UcbScorer::new(&model, user_ctr, &UcbOptions::default())?;
+
+    let mut best: Option<UcbResult> = None;
+    for article in articles {
+        if let Some((term1, term2)) = scorer.score(&article.article_id, &article.embedding)? {
+            let candidate = UcbResult { article_id: article.article_id, ucb: term1 + term2 };
+            // Same order as get_ucb_values_sorted, so NaN never wins over a real score
+            if best.as_ref().is_none_or(|b| rank_ucb(&candidate, b) == Ordering::Less) {
+                best = Some(candidate);
+            }
+        }
+    }
+
+    let best = best.ok_or_else(|| JsValue::from_str("No article matches the model dimension."))?;
+
+    Ok(serde_wasm_bindgen::to_value(&best)?)
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UncertaintyResult {
+    #[serde(rename = "articleId")]
+    pub article_id: String,
+    pub uncertainty: f64,
+}
+
+/// Model uncertainty `sqrt(x^T A_inv x)` per article, i.e. the UCB exploration
+/// term without alpha, for building active-learning queues.
+#[wasm_bindgen]
+pub fn get_uncertainty_scores_bulk(
+    model_js: JsValue,
+    articles_js: JsValue,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let articles: Vec<Article> = serde_wasm_bindgen::from_value(articles_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let options = UcbOptions {
+        alpha_override: Some(1.0),
+        ..UcbOptions::default()
+    };
+    let scorer = UcbScorer::new(&model, 0.0, &options)?;
+
+    let mut results: Vec<UncertaintyResult> = Vec::with_capacity(articles.len());
+    for article in articles {
+        if let Some((_, uncertainty)) = scorer.score(&article.article_id, &article.embedding)? {
+            results.push(UncertaintyResult {
+                article_id: article.article_id,
+                uncertainty,
+            });
+        }
+    }
+
+    Ok(serde_wasm_bindgen::to_value(&results)?)
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ProjectionResult {
+    #[serde(rename = "articleId")]
+    pub article_id: String,
+    pub projection: f64,
+}
+
+/// Signed scalar projection `x · (hat_theta / ||hat_theta||)` per article: how
+/// far each embedding points along the learned preference direction. A zero
+/// theta (e.g. a fresh model) projects everything to 0.
+#[wasm_bindgen]
+pub fn project_onto_theta_bulk(
+    model_js: JsValue,
+    articles_js: JsValue,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize BanditModel: {}", e)))?;
+    let articles: Vec<Article> = serde_wasm_bindgen::from_value(articles_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize articles: {}", e)))?;
+
+    validate_bandit_model(&model).map_err(|e| JsValue::from_str(&e))?;
+
+    let d = model.dimension;
+    let a_inv = ArrayView2::from_shape((d, d), &model.a_inv)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let b = ArrayView::from(&model.b);
+
+    let hat_theta = a_inv.dot(&b);
+    let theta_norm = hat_theta.dot(&hat_theta).sqrt();
+
+    let mut results: Vec<ProjectionResult> = Vec::with_capacity(articles.len());
+
+    for article in articles {
+        if article.embedding.len() != d {
+            log!("Skipping article {} due to embedding dimension mismatch.", article.article_id);
+            continue;
+        }
+
+        let projection = if theta_norm == 0.0 {
+            0.0
+        } else {
+            ArrayView::from(&article.embedding).dot(&hat_theta) / theta_norm
+        };
+
+        results.push(ProjectionResult {
+            article_id: article.article_id,
+            projection,
+        });
+    }
+
+    Ok(serde_wasm_bindgen::to_value(&results)?)
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ThetaSimilarity {
+    #[serde(rename = "articleId")]
+    pub article_id: String,
+    pub similarity: f64,
+}
+
+/// Cosine similarity between each article embedding and `hat_theta`, i.e. a
+/// pure content signal along the learned preference direction. A zero theta
+/// (e.g. a fresh model) gives 0 for every article.
+#[wasm_bindgen]
+pub fn theta_cosine_similarity_bulk(
+    model_js: JsValue,
+    articles_js: JsValue,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize BanditModel: {}", e)))?;
+    let articles: Vec<Article> = serde_wasm_bindgen::from_value(articles_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize articles: {}", e)))?;
+
+    validate_bandit_model(&model).map_err(|e| JsValue::from_str(&e))?;
+
+    let d = model.dimension;
+    let a_inv = ArrayView2::from_shape((d, d), &model.a_inv)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let b = ArrayView::from(&model.b);
+
+    let hat_theta = a_inv.dot(&b).to_vec();
+    let theta_mag = magnitude(&hat_theta);
+
+    let mut results: Vec<ThetaSimilarity> = Vec::with_capacity(articles.len());
+
+    for article in articles {
+        if article.embedding.len() != d {
+            log!("Skipping article {} due to embedding dimension mismatch.", article.article_id);
+            continue;
+        }
+
+        // cosine_with_magnitude already returns 0 for a zero theta
+        results.push(ThetaSimilarity {
+            similarity: cosine_with_magnitude(&hat_theta, theta_mag, &article.embedding),
+            article_id: article.article_id,
+        });
+    }
+
+    Ok(serde_wasm_bindgen::to_value(&results)?)
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SelectionProbability {
+    #[serde(rename = "articleId")]
+    pub article_id: String,
+    pub probability: f64,
+}
+
+/// Turns `get_ucb_values_bulk` output into selection probabilities with
+/// `softmax(ucb / temperature)`. The max score is subtracted before `exp` so
+/// large UCB values don't overflow.
+#[wasm_bindgen]
+pub fn softmax_scores(ucb_results_js: JsValue, temperature: f64) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let ucb_results: Vec<UcbResult> = serde_wasm_bindgen::from_value(ucb_results_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize UCB results: {}", e)))?;
+
+    if !temperature.is_finite() || temperature <= 0.0 {
+        return Err(JsValue::from_str("temperature must be a positive finite number."));
+    }
+
+    let max_ucb = ucb_results.iter().map(|r| r.ucb).fold(f64::NEG_INFINITY, f64::max);
+    let weights: Vec<f64> = ucb_results
+        .iter()
+        .map(|r| ((r.ucb - max_ucb) / temperature).exp())
+        .collect();
+    let total: f64 = weights.iter().sum();
+
+    let probabilities: Vec<SelectionProbability> = ucb_results
+        .into_iter()
+        .zip(weights)
+        .map(|(r, w)| SelectionProbability {
+            article_id: r.article_id,
+            probability: w / total,
+        })
+        .collect();
+
+    Ok(serde_wasm_bindgen::to_value(&probabilities)?)
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ArticlePopularity {
+    #[serde(rename = "articleId")]
+    pub article_id: String,
+    pub popularity: f64,
+}
+
+/// Cold-start scoring: `blend * ucb + (1 - blend) * popularity`, so new users
+/// lean on global popularity until their model matures. Articles missing
+/// from `popularity` count as 0. Output has the shape of `get_ucb_values_bulk`.
+#[wasm_bindgen]
+pub fn get_ucb_values_cold_start(
+    model_js: JsValue,
+    articles_js: JsValue,
+    user_ctr: f64,
+    popularity_js: JsValue,
+    blend: f64,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let articles: Vec<Article> = serde_wasm_bindgen::from_value(articles_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let popularity: Vec<ArticlePopularity> = serde_wasm_bindgen::from_value(popularity_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize popularity: {}", e)))?;
+
+    if !(0.0..=1.0).contains(&blend) {
+        return Err(JsValue::from_str("blend must be between 0 and 1."));
+    }
+
+    let popularity_by_id: HashMap<&str, f64> = popularity
+        .iter()
+        .map(|p| (p.article_id.as_str(), p.popularity))
+        .collect();
+
+    let results: Vec<UcbResult> = compute_ucb_detailed(&model, articles, user_ctr, &UcbOptions::default())?
+        .into_iter()
+        .map(|r| {
+            let pop = popularity_by_id.get(r.article_id.as_str()).copied().unwrap_or(0.0);
+            UcbResult {
+                ucb: blend * r.ucb + (1.0 - blend) * pop,
+                article_id: r.article_id,
+            }
+        })
+        .collect();
+
+    Ok(serde_wasm_bindgen::to_value(&results)?)
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AuditedUcbResult {
+    #[serde(rename = "articleId")]
+    pub article_id: String,
+    pub ucb: f64,
+    pub embedding: Vec<f64>,
+}
+
+/// `get_ucb_values_bulk` that also echoes the embedding each score was
+/// computed from (L2-normalized when `normalize` is set), for auditing what
+/// WASM actually saw. Opt-in, since the echoed embeddings roughly double the
+/// output size.
+#[wasm_bindgen]
+pub fn get_ucb_values_bulk_audit(
+    model_js: JsValue,
+    articles_js: JsValue,
+    user_ctr: f64,
+    options_js: Option<JsValue>,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let articles: Vec<Article> = serde_wasm_bindgen::from_value(articles_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let options = parse_ucb_options(options_js)?;
+
+    let mut results: Vec<AuditedUcbResult> = Vec::with_capacity(articles.len());
+
+    if !articles.is_empty() {
+        let scorer = UcbScorer::new(&model, user_ctr, &options)?;
+        for mut article in articles {
+            if let Some((term1, term2)) = scorer.score(&article.article_id, &article.embedding)? {
+                if options.normalize {
+                    l2_normalize(&mut article.embedding);
+                }
+                results.push(AuditedUcbResult {
+                    article_id: article.article_id,
+                    ucb: term1 + term2,
+                    embedding: article.embedding,
+                });
+            }
+        }
+    }
+
+    Ok(serde_wasm_bindgen::to_value(&results)?)
+}
+
+fn default_annealing_power() -> f64 {
+    0.5
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AnnealingSchedule {
+    #[serde(rename = "alpha0")]
+    pub alpha0: f64,
+    // alpha = alpha0 / (1 + update_count)^power; 0.5 is the 1/sqrt schedule
+    #[serde(default = "default_annealing_power")]
+    pub power: f64,
+    #[serde(rename = "minAlpha", default)]
+    pub min_alpha: f64,
+    // CTR for the fallback alpha when the model has no update_count
+    #[serde(rename = "userCtr", default)]
+    pub user_ctr: f64,
+}
+
+/// UCB scoring with exploration that anneals as the model matures:
+/// `alpha = max(alpha0 / (1 + update_count)^power, minAlpha)`. Models without
+/// an `update_count` fall back to the CTR-based alpha, using the stored CTR
+/// when the model has one and `userCtr` otherwise.
+#[wasm_bindgen]
+pub fn get_ucb_values_annealed(
+    model_js: JsValue,
+    articles_js: JsValue,
+    schedule_js: JsValue,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let articles: Vec<Article> = serde_wasm_bindgen::from_value(articles_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let schedule: AnnealingSchedule = serde_wasm_bindgen::from_value(schedule_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize annealing schedule: {}", e)))?;
+
+    if !schedule.alpha0.is_finite() || schedule.alpha0 < 0.0 {
+        return Err(JsValue::from_str("alpha0 must be a non-negative finite number."));
+    }
+    if !schedule.power.is_finite() || schedule.power < 0.0 {
+        return Err(JsValue::from_str("power must be a non-negative finite number."));
+    }
+    if !schedule.min_alpha.is_finite() || schedule.min_alpha < 0.0 {
+        return Err(JsValue::from_str("minAlpha must be a non-negative finite number."));
+    }
+
+    let options = match model.update_count {
+        Some(count) => UcbOptions {
+            alpha_override: Some(
+                (schedule.alpha0 / (1.0 + count as f64).powf(schedule.power)).max(schedule.min_alpha),
+            ),
+            ..UcbOptions::default()
+        },
+        None => UcbOptions {
+            use_stored_ctr: true,
+            ..UcbOptions::default()
+        },
+    };
+
+    let results: Vec<UcbResult> = compute_ucb_detailed(&model, articles, schedule.user_ctr, &options)?
+        .into_iter()
+        .map(|r| UcbResult { article_id: r.article_id, ucb: r.ucb })
+        .collect();
+
+    Ok(serde_wasm_bindgen::to_value(&results)?)
+}
+
+/// Multi-objective UCB: exploitation is `sum_k weights[k] * x . theta_k` with
+/// `theta_k = A_inv B[:, k]`, plus the usual exploration bonus from the shared
+/// `A_inv`, with alpha derived from `user_ctr` as in `get_ucb_values_bulk`.
+/// `weights` needs one entry per objective. Output has the same shape as
+/// `get_ucb_values_bulk`.
+#[wasm_bindgen]
+pub fn get_ucb_values_multi(
+    model_js: JsValue,
+    articles_js: JsValue,
+    weights_js: JsValue,
+    user_ctr: f64,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let articles: Vec<Article> = serde_wasm_bindgen::from_value(articles_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let weights: Vec<f64> = serde_wasm_bindgen::from_value(weights_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize weights: {}", e)))?;
+
+    validate_bandit_model(&model).map_err(|e| JsValue::from_str(&e))?;
+    let multi = model
+        .multi
+        .as_ref()
+        .ok_or_else(|| JsValue::from_str("Model is not multi-objective; call update_bandit_model_multi first."))?;
+    if weights.len() != multi.objectives {
+        return Err(JsValue::from_str(&format!(
+            "Weights length mismatch: model has {} objectives, got {}",
+            multi.objectives,
+            weights.len()
+        )));
+    }
+    if weights.iter().any(|w| !w.is_finite()) {
+        return Err(JsValue::from_str("Weights contain non-finite values (NaN/Inf)."));
+    }
+
+    // theta is linear in b, so scoring against B w gives the weighted exploitation
+    let b_multi = ArrayView2::from_shape((model.dimension, multi.objectives), &multi.b)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let combined = BanditModel {
+        a_inv: model.a_inv.clone(),
+        b: b_multi.dot(&ArrayView::from(&weights)).into_raw_vec_and_offset().0,
+        dimension: model.dimension,
+        shared: None,
+        impressions: model.impressions,
+        clicks: model.clicks,
+        update_count: model.update_count,
+        multi: None,
+    };
+
+    let ucb_results: Vec<UcbResult> = compute_ucb_detailed(&combined, articles, user_ctr, &UcbOptions::default())?
+        .into_iter()
+        .map(|r| UcbResult { article_id: r.article_id, ucb: r.ucb })
+        .collect();
+
+    Ok(serde_wasm_bindgen::to_value(&ucb_results)?)
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ConfidenceBounds {
+    #[serde(rename = "articleId")]
+    pub article_id: String,
+    pub lower: f64,
+    pub mean: f64,
+    pub upper: f64,
+}
+
+/// `mean = x · hat_theta` with `mean ∓ alpha * sqrt(x^T A_inv x)` on either
+/// side, using the same alpha and exploration term as `get_ucb_values_bulk`,
+/// so `upper` equals its `ucb`.
+#[wasm_bindgen]
+pub fn get_confidence_bounds_bulk(
+    model_js: JsValue,
+    articles_js: JsValue,
+    user_ctr: f64,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let articles: Vec<Article> = serde_wasm_bindgen::from_value(articles_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let bounds: Vec<ConfidenceBounds> = compute_ucb_detailed(&model, articles, user_ctr, &UcbOptions::default())?
+        .into_iter()
+        .map(|r| ConfidenceBounds {
+            article_id: r.article_id,
+            lower: r.exploitation - r.exploration,
+            mean: r.exploitation,
+            upper: r.ucb,
+        })
+        .collect();
+
+    Ok(serde_wasm_bindgen::to_value(&bounds)?)
+}
+
+// Lower-triangular Cholesky factor of a positive semi-definite matrix.
+// Zero pivots (within tolerance) are allowed so that rank-deficient A_inv still
+// yields a usable factor; a clearly negative pivot means the matrix is not PSD.
+fn cholesky_psd(a: &ArrayView2<f64>) -> Result<Array2<f64>, String> {
+    const EPS: f64 = 1e-10;
+    let d = a.nrows();
+    let mut l = Array2::<f64>::zeros((d, d));
+
+    for j in 0..d {
+        let mut diag = a[[j, j]];
+        for k in 0..j {
+            diag -= l[[j, k]] * l[[j, k]];
+        }
+        if !diag.is_finite() || diag < -EPS {
+            return Err(format!(
+                "A_inv is not positive semi-definite (pivot {} at index {}); cannot sample.",
+                diag, j
+            ));
+        }
+        if diag <= EPS {
+            // Degenerate direction: no variance along this axis.
+            continue;
+        }
+        let l_jj = diag.sqrt();
+        l[[j, j]] = l_jj;
+        for i in (j + 1)..d {
+            let mut v = a[[i, j]];
+            for k in 0..j {
+                v -= l[[i, k]] * l[[j, k]];
+            }
+            l[[i, j]] = v / l_jj;
+        }
+    }
+
+    Ok(l)
+}
+
+/// Thompson Sampling counterpart of `get_ucb_values_bulk`.
+///
+/// Draws a single `theta ~ N(hat_theta, alpha^2 * A_inv)` per call and scores
+/// every article by `x · theta`. Alpha is resolved from `user_ctr` and the
+/// options exactly as in `get_ucb_values_bulk`, and `normalize` is honoured;
+/// the other options only affect the UCB bonus and are ignored. The sampled
+/// score is returned in the `ucb` field so callers can reuse the `UcbResult`
+/// handling they already have.
+/// A_inv must be positive semi-definite for the Cholesky draw; otherwise an
+/// error is returned.
+#[wasm_bindgen]
+pub fn sample_thompson_bulk(
+    model_js: JsValue,
+    articles_js: JsValue,
+    seed: u64,
+    user_ctr: f64,
+    options_js: Option<JsValue>,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let articles: Vec<Article> = serde_wasm_bindgen::from_value(articles_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let options = parse_ucb_options(options_js)?;
+
+    validate_bandit_model(&model).map_err(|e| JsValue::from_str(&e))?;
+
+    let d = model.dimension;
+
+    let a_inv = ArrayView2::from_shape((d, d), &model.a_inv)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let b = ArrayView::from(&model.b);
+
+    let alpha = resolve_alpha(&model, user_ctr, &options);
+
+    let hat_theta = a_inv.dot(&b);
+    let l = cholesky_psd(&a_inv).map_err(|e| JsValue::from_str(&e))?;
+
+    // theta = hat_theta + alpha * L * z, z ~ N(0, I)
+    let mut rng = rng::SplitMix64::new(seed);
+    let z: Array1<f64> = (0..d).map(|_| rng.next_gaussian()).collect();
+    let sampled_theta = hat_theta + l.dot(&z) * alpha;
+
+    let mut results: Vec<UcbResult> = Vec::with_capacity(articles.len());
+
+    for article in articles {
+        if article.embedding.len() != d {
+            log!("Skipping article {} due to embedding dimension mismatch.", article.article_id);
+            continue;
+        }
+        let mut embedding = article.embedding;
+        if options.normalize {
+            l2_normalize(&mut embedding);
+        }
+        let x = ArrayView::from(&embedding);
+
+        results.push(UcbResult {
+            article_id: article.article_id,
+            ucb: x.dot(&sampled_theta),
+        });
+    }
+
+    Ok(serde_wasm_bindgen::to_value(&results)?)
+}
+
+// Structural checks shared by every function that reads or mutates a model.
+// Collects every failure rather than stopping at the first.
+fn bandit_model_errors(model: &BanditModel) -> Vec<String> {
+    let d = model.dimension;
+    let mut errors = Vec::new();
+
+    // 1) reject zero-dimension early
+    if d == 0 {
+        errors.push("Bandit model dimension cannot be zero.".to_string());
+    }
+
+    // 2) basic length checks for a_inv and b
+    if model.a_inv.len() != d * d {
+        errors.push(format!(
+            "Bandit model A_inv length mismatch: expected {}, got {}",
+            d * d,
+            model.a_inv.len()
+        ));
+    }
+
+    if model.b.len() != d {
+        errors.push(format!(
+            "Bandit model b length mismatch: expected {}, got {}",
+            d,
+            model.b.len()
+        ));
+    }
+
+    // 3) check finite elements in a_inv and b
+    if model.a_inv.iter().any(|&v| !v.is_finite()) {
+        errors.push("Bandit model A_inv contains non-finite values (NaN/Inf).".to_string());
+    }
+    if model.b.iter().any(|&v| !v.is_finite()) {
+        errors.push("Bandit model b contains non-finite values (NaN/Inf).".to_string());
+    }
+
+    // 4) multi-objective reward vectors, when present
+    if let Some(multi) = &model.multi {
+        if multi.objectives == 0 {
+            errors.push("Multi-objective model must have at least one objective.".to_string());
+        }
+        if multi.b.len() != d * multi.objectives {
+            errors.push(format!(
+                "Multi-objective b length mismatch: expected {}, got {}",
+                d * multi.objectives,
+                multi.b.len()
+            ));
+        }
+        if multi.b.iter().any(|&v| !v.is_finite()) {
+            errors.push("Multi-objective b contains non-finite values (NaN/Inf).".to_string());
+        }
+    }
+
+    // 5) hybrid shared component, when present: lengths and finiteness
+    if let Some(shared) = &model.shared {
+        if let Err(e) = validate_shared_component(shared, d) {
+            errors.push(e);
+        }
+    }
+
+    errors
+}
+
+fn validate_bandit_model(model: &BanditModel) -> Result<(), String> {
+    match bandit_model_errors(model).into_iter().next() {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+fn validate_embedding(embedding: &[f64], d: usize) -> Result<(), String> {
+    if embedding.iter().any(|&v| !v.is_finite()) {
+        return Err("Embedding contains non-finite values (NaN/Inf).".to_string());
+    }
+    if embedding.len() != d {
+        return Err("Embedding dimension mismatch.".to_string());
+    }
+    Ok(())
+}
+
+// Single-objective updates would move A_inv without the per-objective b
+// vectors, leaving every later multi-objective score on a stale b.
+fn ensure_single_objective(model: &BanditModel) -> Result<(), String> {
+    if model.multi.is_some() {
+        return Err("Model is multi-objective; update it with update_bandit_model_multi.".to_string());
+    }
+    Ok(())
+}
+
+// Rank-1 Sherman-Morrison update of A_inv plus b += reward * x, in place.
+fn sherman_morrison_update(
+    a_inv: &mut Array2<f64>,
+    b: &mut Array1<f64>,
+    x: ArrayView1<f64>,
+    reward: f64,
+) -> Result<(), String> {
+    // A NaN/Inf reward would silently poison b
+    if !reward.is_finite() {
+        return Err("Reward is non-finite (NaN/Inf).".to_string());
+    }
+
+    sherman_morrison_a_inv(a_inv, x)?;
+
+    // ensure shapes match before addition
+    if b.len() != x.len() {
+        return Err("Shape mismatch when updating b.".to_string());
+    }
+    b.scaled_add(reward, &x);
+
+    Ok(())
+}
+
+// The A_inv half of the update: A_inv <- A_inv - (A_inv x x^T A_inv) / (1 + x^T A_inv x).
+fn sherman_morrison_a_inv(a_inv: &mut Array2<f64>, x: ArrayView1<f64>) -> Result<(), String> {
+    // Sherman-Morrison computation
+    let a_inv_x = a_inv.dot(&x);
+    let x_t_a_inv_x = x.dot(&a_inv_x);
+    let denominator = 1.0 + x_t_a_inv_x;
+
+    // 4) robust near-zero check
+    const EPS: f64 = 1e-12;
+    if !denominator.is_finite() {
+        return Err("Denominator is non-finite (NaN/Inf) in Sherman-Morrison update.".to_string());
+    }
+    if denominator.abs() < EPS {
+        return Err("Denominator too small in Sherman-Morrison update (numerical instability).".to_string());
+    }
+
+    // 5) compute numerator safely (shapes already validated)
+    let numerator_matrix = a_inv_x
+        .insert_axis(ndarray::Axis(1))
+        .dot(&x.insert_axis(ndarray::Axis(0)).dot(&*a_inv));
+
+    // 6) subtract
+    *a_inv -= &(numerator_matrix / denominator);
+
+    Ok(())
+}
+
+// Deserialize, validate, apply one Sherman-Morrison update and serialize back.
+// Shared by the update variants that only differ in how the reward is derived.
+fn apply_single_update(model_js: JsValue, embedding: &[f64], reward: f64) -> Result<JsValue, JsValue> {
+    let mut model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize BanditModel: {}", e)))?;
+
+    let d = model.dimension;
+
+    validate_bandit_model(&model).map_err(|e| JsValue::from_str(&e))?;
+    ensure_single_objective(&model).map_err(|e| JsValue::from_str(&e))?;
+    validate_embedding(embedding, d).map_err(|e| JsValue::from_str(&e))?;
+
+    let x = ArrayView::from(embedding);
+    let mut a_inv = Array2::from_shape_vec((d, d), model.a_inv)
+        .map_err(|e| JsValue::from_str(&format!("A_inv shape error: {}", e)))?;
+    let mut b = Array1::from(model.b);
+
+    sherman_morrison_update(&mut a_inv, &mut b, x, reward).map_err(|e| JsValue::from_str(&e))?;
+
+    // finalize
+    model.a_inv = a_inv.into_raw_vec_and_offset().0;
+    model.b = b.into_raw_vec_and_offset().0;
+    model.record_updates(1);
+
+    serde_wasm_bindgen::to_value(&model).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+#[wasm_bindgen]
+pub fn update_bandit_model(
+    model_js: JsValue,
+    embedding: &[f64],
+    reward: f64,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    apply_single_update(model_js, embedding, reward)
+}
+
+// A <- gamma * A and b <- gamma * b. Since we store A_inv, A_inv <- A_inv / gamma.
+fn apply_decay(a_inv: &mut Array2<f64>, b: &mut Array1<f64>, gamma: f64) -> Result<(), String> {
+    if !gamma.is_finite() || gamma <= 0.0 || gamma > 1.0 {
+        return Err("gamma must satisfy 0 < gamma <= 1.".to_string());
+    }
+    *a_inv /= gamma;
+    *b *= gamma;
+    Ok(())
+}
+
+/// `update_bandit_model` with a forgetting factor: past evidence (A and b) is
+/// scaled by `gamma` before the rank-1 update.
+///
+/// Every call multiplies A_inv by `1 / gamma`, and the ridge prior decays along
+/// with the data. With small `gamma` A_inv grows quickly in directions that
+/// see no new clicks, which inflates the exploration bonus and can eventually
+/// overflow or make the Sherman-Morrison denominator unreliable. Values close
+/// to 1 (e.g. 0.95-0.999) are the safe range.
+#[wasm_bindgen]
+pub fn update_bandit_model_decayed(
+    model_js: JsValue,
+    embedding: &[f64],
+    reward: f64,
+    gamma: f64,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let mut model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize BanditModel: {}", e)))?;
+
+    let d = model.dimension;
+
+    validate_bandit_model(&model).map_err(|e| JsValue::from_str(&e))?;
+    ensure_single_objective(&model).map_err(|e| JsValue::from_str(&e))?;
+    validate_embedding(embedding, d).map_err(|e| JsValue::from_str(&e))?;
+
+    let x = ArrayView::from(embedding);
+    let mut a_inv = Array2::from_shape_vec((d, d), model.a_inv)
+        .map_err(|e| JsValue::from_str(&format!("A_inv shape error: {}", e)))?;
+    let mut b = Array1::from(model.b);
+
+    apply_decay(&mut a_inv, &mut b, gamma).map_err(|e| JsValue::from_str(&e))?;
+    sherman_morrison_update(&mut a_inv, &mut b, x, reward).map_err(|e| JsValue::from_str(&e))?;
+
+    model.a_inv = a_inv.into_raw_vec_and_offset().0;
+    model.b = b.into_raw_vec_and_offset().0;
+    model.record_updates(1);
+
+    serde_wasm_bindgen::to_value(&model).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// `update_bandit_model` with the reward clamped into `[min_reward, max_reward]`
+/// before it is applied to b.
+#[wasm_bindgen]
+pub fn update_bandit_model_clipped(
+    model_js: JsValue,
+    embedding: &[f64],
+    reward: f64,
+    min_reward: f64,
+    max_reward: f64,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    if min_reward.is_nan() || max_reward.is_nan() || min_reward > max_reward {
+        return Err(JsValue::from_str(&format!(
+            "min_reward ({}) must be less than or equal to max_reward ({}).",
+            min_reward, max_reward
+        )));
+    }
+
+    apply_single_update(model_js, embedding, reward.clamp(min_reward, max_reward))
+}
+
+/// `update_bandit_model` with separate scales for positive and negative
+/// feedback: the reward is multiplied by `positive_scale` when `reward >= 0`
+/// and by `negative_scale` otherwise before updating b.
+#[wasm_bindgen]
+pub fn update_bandit_model_asymmetric(
+    model_js: JsValue,
+    embedding: &[f64],
+    reward: f64,
+    positive_scale: f64,
+    negative_scale: f64,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    if !positive_scale.is_finite() || positive_scale < 0.0 {
+        return Err(JsValue::from_str("positive_scale must be a non-negative finite number."));
+    }
+    if !negative_scale.is_finite() || negative_scale < 0.0 {
+        return Err(JsValue::from_str("negative_scale must be a non-negative finite number."));
+    }
+
+    let scale = if reward >= 0.0 { positive_scale } else { negative_scale };
+
+    apply_single_update(model_js, embedding, reward * scale)
+}
+
+#[derive(Serialize)]
+pub struct TrackedUpdate {
+    pub model: BanditModel,
+    #[serde(rename = "thetaDeltaNorm")]
+    pub theta_delta_norm: f64,
+}
+
+/// `update_bandit_model` that also reports `||hat_theta_after - hat_theta_before||`,
+/// so callers can watch a model converge.
+#[wasm_bindgen]
+pub fn update_bandit_model_tracked(
+    model_js: JsValue,
+    embedding: &[f64],
+    reward: f64,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let mut model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize BanditModel: {}", e)))?;
+
+    let d = model.dimension;
+
+    validate_bandit_model(&model).map_err(|e| JsValue::from_str(&e))?;
+    ensure_single_objective(&model).map_err(|e| JsValue::from_str(&e))?;
+    validate_embedding(embedding, d).map_err(|e| JsValue::from_str(&e))?;
+
+    let x = ArrayView::from(embedding);
+    let mut a_inv = Array2::from_shape_vec((d, d), model.a_inv)
+        .map_err(|e| JsValue::from_str(&format!("A_inv shape error: {}", e)))?;
+    let mut b = Array1::from(model.b);
+
+    let theta_before = a_inv.dot(&b);
+    sherman_morrison_update(&mut a_inv, &mut b, x, reward).map_err(|e| JsValue::from_str(&e))?;
+    let theta_after = a_inv.dot(&b);
+
+    let theta_delta_norm = (&theta_after - &theta_before).mapv(|v| v * v).sum().sqrt();
+
+    model.a_inv = a_inv.into_raw_vec_and_offset().0;
+    model.b = b.into_raw_vec_and_offset().0;
+    model.record_updates(1);
+
+    let result = TrackedUpdate { model, theta_delta_norm };
+    serde_wasm_bindgen::to_value(&result).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct StagedScores {
+    pub results: Vec<UcbResult>,
+    // The scored embeddings, to be handed back to `commit_reward`
+    #[serde(rename = "stagedToken")]
+    pub staged_token: Vec<Article>,
+}
+
+/// `get_ucb_values_bulk` that also returns a `stagedToken` holding the exact
+/// embeddings that were scored. Pass it to `commit_reward` later so the update
+/// uses the same embedding even if the article store has changed since.
+#[wasm_bindgen]
+pub fn score_and_stage(
+    model_js: JsValue,
+    articles_js: JsValue,
+    user_ctr: f64,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let articles: Vec<Article> = serde_wasm_bindgen::from_value(articles_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let mut staged = StagedScores {
+        results: Vec::with_capacity(articles.len()),
+        staged_token: Vec::with_capacity(articles.len()),
+    };
+
+    if !articles.is_empty() {
+        let scorer = UcbScorer::new(&model, user_ctr, &UcbOptions::default())?;
+        for article in articles {
+            if let Some((term1, term2)) = scorer.score(&article.article_id, &article.embedding)? {
+                staged.results.push(UcbResult {
+                    article_id: article.article_id.clone(),
+                    ucb: term1 + term2,
+                });
+                staged.staged_token.push(article);
+            }
+        }
+    }
+
+    Ok(serde_wasm_bindgen::to_value(&staged)?)
+}
+
+/// Applies `update_bandit_model` with the embedding staged for `article_id`
+/// by `score_and_stage`.
+#[wasm_bindgen]
+pub fn commit_reward(
+    model_js: JsValue,
+    staged_token_js: JsValue,
+    article_id: &str,
+    reward: f64,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let staged: Vec<Article> = serde_wasm_bindgen::from_value(staged_token_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize staged token: {}", e)))?;
+
+    let article = staged
+        .iter()
+        .find(|a| a.article_id == article_id)
+        .ok_or_else(|| JsValue::from_str(&format!("Article {} is not in the staged token.", article_id)))?;
+
+    apply_single_update(model_js, &article.embedding, reward)
+}
+
+/// Multi-objective update: one shared Sherman-Morrison step on `A_inv`, then
+/// `B[:, k] += rewards[k] * x` for each of the m objectives. The first call on
+/// a plain model starts `multi` at zero with `m = rewards.length`; later calls
+/// must pass the same number of rewards. The single-objective `b` is left as is.
+#[wasm_bindgen]
+pub fn update_bandit_model_multi(
+    model_js: JsValue,
+    embedding: &[f64],
+    rewards_js: JsValue,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let mut model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize BanditModel: {}", e)))?;
+    let rewards: Vec<f64> = serde_wasm_bindgen::from_value(rewards_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize rewards: {}", e)))?;
+
+    let d = model.dimension;
+
+    validate_bandit_model(&model).map_err(|e| JsValue::from_str(&e))?;
+    validate_embedding(embedding, d).map_err(|e| JsValue::from_str(&e))?;
+    if model.shared.is_some() {
+        return Err(JsValue::from_str(
+            "Model has a shared component; multi-objective hybrid models are not supported.",
+        ));
+    }
+    if rewards.is_empty() {
+        return Err(JsValue::from_str("rewards must not be empty."));
+    }
+    if rewards.iter().any(|r| !r.is_finite()) {
+        return Err(JsValue::from_str("Reward is non-finite (NaN/Inf)."));
+    }
+
+    let m = rewards.len();
+    let mut multi = model.multi.take().unwrap_or(MultiObjective { b: vec![0.0; d * m], objectives: m });
+    if multi.objectives != m {
+        return Err(JsValue::from_str(&format!(
+            "Rewards length mismatch: model has {} objectives, got {}",
+            multi.objectives, m
+        )));
+    }
+
+    let x = ArrayView::from(embedding);
+    let mut a_inv = Array2::from_shape_vec((d, d), model.a_inv)
+        .map_err(|e| JsValue::from_str(&format!("A_inv shape error: {}", e)))?;
+    sherman_morrison_a_inv(&mut a_inv, x).map_err(|e| JsValue::from_str(&e))?;
+
+    for (i, &xi) in embedding.iter().enumerate() {
+        for (k, &r) in rewards.iter().enumerate() {
+            multi.b[i * m + k] += r * xi;
+        }
+    }
+
+    model.a_inv = a_inv.into_raw_vec_and_offset().0;
+    model.multi = Some(multi);
+    model.record_updates(1);
+
+    serde_wasm_bindgen::to_value(&model).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ModelUpdate {
+    pub embedding: Vec<f64>,
+    pub reward: f64,
+}
+
+/// Applies a whole session of `{ embedding, reward }` updates in order and
+/// returns the final model, so active users cross the JS/WASM boundary once.
+/// Stops at the first failing update and reports its index.
+#[wasm_bindgen]
+pub fn update_bandit_model_batch(
+    model_js: JsValue,
+    updates_js: JsValue,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let mut model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize BanditModel: {}", e)))?;
+    let updates: Vec<ModelUpdate> = serde_wasm_bindgen::from_value(updates_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize updates: {}", e)))?;
+
+    let d = model.dimension;
+
+    validate_bandit_model(&model).map_err(|e| JsValue::from_str(&e))?;
+    ensure_single_objective(&model).map_err(|e| JsValue::from_str(&e))?;
+
+    let mut a_inv = Array2::from_shape_vec((d, d), model.a_inv)
+        .map_err(|e| JsValue::from_str(&format!("A_inv shape error: {}", e)))?;
+    let mut b = Array1::from(model.b);
+
+    for (i, update) in updates.iter().enumerate() {
+        validate_embedding(&update.embedding, d)
+            .and_then(|_| {
+                sherman_morrison_update(&mut a_inv, &mut b, ArrayView::from(&update.embedding), update.reward)
+            })
+            .map_err(|e| JsValue::from_str(&format!("Update {} failed: {}", i, e)))?;
+    }
+
+    model.a_inv = a_inv.into_raw_vec_and_offset().0;
+    model.b = b.into_raw_vec_and_offset().0;
+    model.record_updates(updates.len());
+
+    serde_wasm_bindgen::to_value(&model).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct WeightedModelUpdate {
+    pub embedding: Vec<f64>,
+    pub reward: f64,
+    pub weight: f64,
+}
+
+/// `update_bandit_model_batch` where each interaction carries a confidence
+/// `weight`: A gains `weight * x x^T` and b gains `weight * reward * x`. This is
+/// the plain rank-1 update on `sqrt(weight) * x` with reward `sqrt(weight) * reward`.
+#[wasm_bindgen]
+pub fn update_bandit_model_weighted_batch(
+    model_js: JsValue,
+    updates_js: JsValue,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let mut model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize BanditModel: {}", e)))?;
+    let updates: Vec<WeightedModelUpdate> = serde_wasm_bindgen::from_value(updates_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize updates: {}", e)))?;
+
+    let d = model.dimension;
+
+    validate_bandit_model(&model).map_err(|e| JsValue::from_str(&e))?;
+    ensure_single_objective(&model).map_err(|e| JsValue::from_str(&e))?;
+
+    let mut a_inv = Array2::from_shape_vec((d, d), model.a_inv)
+        .map_err(|e| JsValue::from_str(&format!("A_inv shape error: {}", e)))?;
+    let mut b = Array1::from(model.b);
+
+    for (i, update) in updates.iter().enumerate() {
+        if !update.weight.is_finite() || update.weight <= 0.0 {
+            return Err(JsValue::from_str(&format!(
+                "Update {} failed: weight must be a positive finite number.",
+                i
+            )));
+        }
+        let scale = update.weight.sqrt();
+        validate_embedding(&update.embedding, d)
+            .and_then(|_| {
+                let x = ArrayView::from(&update.embedding).mapv(|v| v * scale);
+                sherman_morrison_update(&mut a_inv, &mut b, x.view(), update.reward * scale)
+            })
+            .map_err(|e| JsValue::from_str(&format!("Update {} failed: {}", i, e)))?;
+    }
+
+    model.a_inv = a_inv.into_raw_vec_and_offset().0;
+    model.b = b.into_raw_vec_and_offset().0;
+    model.record_updates(updates.len());
+
+    serde_wasm_bindgen::to_value(&model).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TimedModelUpdate {
+    pub embedding: Vec<f64>,
+    pub reward: f64,
+    pub timestamp: f64,
+}
+
+/// Batch update with time-based forgetting. Updates are applied in timestamp
+/// order, and before each one after the first the model is decayed by
+/// `gamma = 0.5^(dt / half_life)`, where `dt` is the gap to the previous
+/// update. The same A_inv growth caveats as `update_bandit_model_decayed`
+/// apply when gaps are long relative to `half_life`.
+#[wasm_bindgen]
+pub fn update_bandit_model_timed_batch(
+    model_js: JsValue,
+    updates_js: JsValue,
+    half_life: f64,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let mut model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize BanditModel: {}", e)))?;
+    let updates: Vec<TimedModelUpdate> = serde_wasm_bindgen::from_value(updates_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize updates: {}", e)))?;
+
+    if !half_life.is_finite() || half_life <= 0.0 {
+        return Err(JsValue::from_str("half_life must be a positive finite number."));
+    }
+    if let Some(i) = updates.iter().position(|u| !u.timestamp.is_finite()) {
+        return Err(JsValue::from_str(&format!("Update {} failed: timestamp is non-finite (NaN/Inf).", i)));
+    }
+
+    let d = model.dimension;
+
+    validate_bandit_model(&model).map_err(|e| JsValue::from_str(&e))?;
+    ensure_single_objective(&model).map_err(|e| JsValue::from_str(&e))?;
+
+    let mut a_inv = Array2::from_shape_vec((d, d), model.a_inv)
+        .map_err(|e| JsValue::from_str(&format!("A_inv shape error: {}", e)))?;
+    let mut b = Array1::from(model.b);
+
+    // Stable sort, so equal timestamps keep their input order
+    let mut order: Vec<usize> = (0..updates.len()).collect();
+    order.sort_by(|&i, &j| updates[i].timestamp.total_cmp(&updates[j].timestamp));
+
+    let mut previous: Option<f64> = None;
+    for i in order {
+        let update = &updates[i];
+        validate_embedding(&update.embedding, d)
+            .and_then(|_| match previous {
+                Some(t) => {
+                    let dt = update.timestamp - t;
+                    let too_long = || {
+                        format!(
+                            "gap of {} since the previous update is too long for half_life {} (decayed A_inv overflows).",
+                            dt, half_life
+                        )
+                    };
+                    // 0.5^(dt / half_life) underflows to 0 long before the gap is absurd
+                    let gamma = 0.5f64.powf(dt / half_life);
+                    if gamma == 0.0 {
+                        return Err(too_long());
+                    }
+                    apply_decay(&mut a_inv, &mut b, gamma)?;
+                    if a_inv.iter().any(|v| !v.is_finite()) {
+                        return Err(too_long());
+                    }
+                    Ok(())
+                }
+                None => Ok(()),
+            })
+            .and_then(|_| {
+                sherman_morrison_update(&mut a_inv, &mut b, ArrayView::from(&update.embedding), update.reward)
+            })
+            .map_err(|e| JsValue::from_str(&format!("Update {} failed: {}", i, e)))?;
+        previous = Some(update.timestamp);
+    }
+
+    model.a_inv = a_inv.into_raw_vec_and_offset().0;
+    model.b = b.into_raw_vec_and_offset().0;
+    model.record_updates(updates.len());
+
+    serde_wasm_bindgen::to_value(&model).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+// Ridge prior A = lambda * I, so a_inv = (1 / lambda) * I and b = 0.
+fn initial_model(dimension: usize, lambda: f64) -> Result<BanditModel, String> {
+    if dimension == 0 {
+        return Err("Bandit model dimension cannot be zero.".to_string());
+    }
+    if !lambda.is_finite() || lambda <= 0.0 {
+        return Err("lambda must be a positive finite number.".to_string());
+    }
+
+    let a_inv = Array2::<f64>::eye(dimension) / lambda;
+
+    Ok(BanditModel {
+        a_inv: a_inv.into_raw_vec_and_offset().0,
+        b: vec![0.0; dimension],
+        dimension,
+        shared: None,
+        impressions: None,
+        clicks: None,
+        update_count: Some(0),
+        multi: None,
+    })
+}
+
+/// Creates a fresh model for ridge regression with `A = lambda * I`, i.e.
+/// `a_inv = (1 / lambda) * I` and `b = 0`.
+#[wasm_bindgen]
+pub fn create_bandit_model(dimension: usize, lambda: f64) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let model = initial_model(dimension, lambda).map_err(|e| JsValue::from_str(&e))?;
+
+    serde_wasm_bindgen::to_value(&model).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Discards everything a model has learned but keeps its dimension. `lambda`
+/// defaults to 1.0, i.e. `a_inv` becomes the identity.
+#[wasm_bindgen]
+pub fn reset_model(model_js: JsValue, lambda: Option<f64>) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize BanditModel: {}", e)))?;
+
+    let reset = initial_model(model.dimension, lambda.unwrap_or(1.0)).map_err(|e| JsValue::from_str(&e))?;
+
+    serde_wasm_bindgen::to_value(&reset).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ModelValidation {
+    pub valid: bool,
+    pub errors: Vec<String>,
+}
+
+/// Runs the same checks as `update_bandit_model` without modifying anything,
+/// and reports every problem found instead of only the first.
+#[wasm_bindgen]
+pub fn validate_model(model_js: JsValue) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize BanditModel: {}", e)))?;
+
+    let errors = bandit_model_errors(&model);
+    let validation = ModelValidation {
+        valid: errors.is_empty(),
+        errors,
+    };
+
+    Ok(serde_wasm_bindgen::to_value(&validation)?)
+}
+
+/// Reads only the `dimension` property, without deserializing `a_inv` or `b`,
+/// so compatibility checks stay cheap for large models.
+#[wasm_bindgen]
+pub fn get_model_dimension(model_js: JsValue) -> Result<usize, JsValue> {
+    utils::set_panic_hook();
+
+    if !model_js.is_object() {
+        return Err(JsValue::from_str("Failed to read BanditModel: expected an object."));
+    }
+    let dimension = js_sys::Reflect::get(&model_js, &JsValue::from_str("dimension"))?;
+
+    match dimension.as_f64() {
+        Some(d) if d >= 0.0 && d.fract() == 0.0 && d <= usize::MAX as f64 => Ok(d as usize),
+        _ => Err(JsValue::from_str(
+            "Failed to read BanditModel: dimension must be a non-negative integer.",
+        )),
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ModelInfo {
+    pub dimension: usize,
+    #[serde(rename = "aInvLen")]
+    pub a_inv_len: usize,
+    #[serde(rename = "bLen")]
+    pub b_len: usize,
+    #[serde(rename = "allFinite")]
+    pub all_finite: bool,
+}
+
+/// Shape summary `{ dimension, aInvLen, bLen, allFinite }`. Unlike
+/// `validate_model` it never fails on a malformed but parseable model, so
+/// callers can inspect what they have.
+#[wasm_bindgen]
+pub fn get_model_info(model_js: JsValue) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize BanditModel: {}", e)))?;
+
+    let info = ModelInfo {
+        dimension: model.dimension,
+        a_inv_len: model.a_inv.len(),
+        b_len: model.b.len(),
+        all_finite: model.a_inv.iter().chain(model.b.iter()).all(|v| v.is_finite()),
+    };
+
+    Ok(serde_wasm_bindgen::to_value(&info)?)
+}
+
+/// Returns the current estimate `hat_theta = A_inv · b`.
+#[wasm_bindgen]
+pub fn get_theta(model_js: JsValue) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize BanditModel: {}", e)))?;
+
+    validate_bandit_model(&model).map_err(|e| JsValue::from_str(&e))?;
+
+    let d = model.dimension;
+    let a_inv = ArrayView2::from_shape((d, d), &model.a_inv)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let b = ArrayView::from(&model.b);
+
+    let hat_theta = a_inv.dot(&b).into_raw_vec_and_offset().0;
+
+    Ok(serde_wasm_bindgen::to_value(&hat_theta)?)
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct FeatureImportance {
+    pub dimension: usize,
+    pub weight: f64,
+    #[serde(rename = "absWeight")]
+    pub abs_weight: f64,
+}
+
+/// Components of `hat_theta = A_inv · b` as `{ dimension, weight, absWeight }`,
+/// sorted by `absWeight` descending (ties by ascending dimension), so the
+/// dimensions that move this user's scores most come first.
+#[wasm_bindgen]
+pub fn get_feature_importance(model_js: JsValue) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize BanditModel: {}", e)))?;
+
+    validate_bandit_model(&model).map_err(|e| JsValue::from_str(&e))?;
+
+    let d = model.dimension;
+    let a_inv = ArrayView2::from_shape((d, d), &model.a_inv)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let hat_theta = a_inv.dot(&ArrayView::from(&model.b));
+
+    let mut importance: Vec<FeatureImportance> = hat_theta
+        .iter()
+        .enumerate()
+        .map(|(dimension, &weight)| FeatureImportance {
+            dimension,
+            weight,
+            abs_weight: weight.abs(),
+        })
+        .collect();
+
+    // Stable sort keeps equal weights in dimension order
+    importance.sort_by(|a, b| b.abs_weight.total_cmp(&a.abs_weight));
+
+    Ok(serde_wasm_bindgen::to_value(&importance)?)
+}
+
+/// Exploitation-only score `x · hat_theta` for a single embedding.
+#[wasm_bindgen]
+pub fn predict_score(model_js: JsValue, embedding: &[f64]) -> Result<f64, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize BanditModel: {}", e)))?;
+
+    let d = model.dimension;
+
+    validate_bandit_model(&model).map_err(|e| JsValue::from_str(&e))?;
+    validate_embedding(embedding, d).map_err(|e| JsValue::from_str(&e))?;
+
+    let a_inv = ArrayView2::from_shape((d, d), &model.a_inv)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let b = ArrayView::from(&model.b);
+    let x = ArrayView::from(embedding);
+
+    Ok(x.dot(&a_inv.dot(&b)))
+}
+
+/// Sherman-Morrison denominator `1 + x^T A_inv x` that `update_bandit_model`
+/// would use for `embedding`. The model is not modified.
+#[wasm_bindgen]
+pub fn dry_run_update(model_js: JsValue, embedding: &[f64]) -> Result<f64, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize BanditModel: {}", e)))?;
+
+    let d = model.dimension;
+
+    validate_bandit_model(&model).map_err(|e| JsValue::from_str(&e))?;
+    validate_embedding(embedding, d).map_err(|e| JsValue::from_str(&e))?;
+
+    let a_inv = ArrayView2::from_shape((d, d), &model.a_inv)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let x = ArrayView::from(embedding);
+
+    Ok(1.0 + x.dot(&a_inv.dot(&x)))
+}
+
+/// Frobenius norm `||A_inv x x^T A_inv / (1 + x^T A_inv x)||_F` of the rank-1
+/// term `update_bandit_model` would subtract from A_inv, without applying it.
+/// The term is the outer product `u v^T` with `u = A_inv x` and
+/// `v = A_inv^T x`, so its norm is `||u|| * ||v|| / |denominator|`.
+#[wasm_bindgen]
+pub fn dry_run_update_norm(model_js: JsValue, embedding: &[f64]) -> Result<f64, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize BanditModel: {}", e)))?;
+
+    let d = model.dimension;
+
+    validate_bandit_model(&model).map_err(|e| JsValue::from_str(&e))?;
+    validate_embedding(embedding, d).map_err(|e| JsValue::from_str(&e))?;
+
+    let a_inv = ArrayView2::from_shape((d, d), &model.a_inv)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let x = ArrayView::from(embedding);
+
+    let u = a_inv.dot(&x);
+    let v = x.dot(&a_inv);
+    let denominator = 1.0 + x.dot(&u);
+
+    if !denominator.is_finite() {
+        return Err(JsValue::from_str("Denominator is non-finite (NaN/Inf) in Sherman-Morrison update."));
+    }
+    if denominator.abs() < 1e-12 {
+        return Err(JsValue::from_str("Denominator too small in Sherman-Morrison update (numerical instability)."));
+    }
+
+    Ok(u.dot(&u).sqrt() * v.dot(&v).sqrt() / denominator.abs())
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct LoggedInteraction {
+    pub embedding: Vec<f64>,
+    pub reward: f64,
+    pub chosen: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PolicyEvaluation {
+    #[serde(rename = "cumulativeReward")]
+    pub cumulative_reward: f64,
+    #[serde(rename = "meanPredictedBest")]
+    pub mean_predicted_best: f64,
+}
+
+/// Offline replay of a reward log. Consecutive entries up to and including a
+/// `chosen` one form a step (one slate shown to the user); trailing entries
+/// without a choice form a final step. `cumulativeReward` sums the rewards of
+/// chosen entries, and `meanPredictedBest` averages the highest `x · hat_theta`
+/// per step, so the gap between them is a simple regret proxy.
+#[wasm_bindgen]
+pub fn evaluate_policy(model_js: JsValue, log_js: JsValue) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize BanditModel: {}", e)))?;
+    let log: Vec<LoggedInteraction> = serde_wasm_bindgen::from_value(log_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize log: {}", e)))?;
+
+    let d = model.dimension;
+
+    validate_bandit_model(&model).map_err(|e| JsValue::from_str(&e))?;
+
+    let a_inv = ArrayView2::from_shape((d, d), &model.a_inv)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let hat_theta = a_inv.dot(&ArrayView::from(&model.b));
+
+    let mut cumulative_reward = 0.0;
+    let mut best_sum = 0.0;
+    let mut steps = 0usize;
+    let mut step_best = f64::NEG_INFINITY;
+
+    for (i, entry) in log.iter().enumerate() {
+        validate_embedding(&entry.embedding, d)
+            .map_err(|e| JsValue::from_str(&format!("Log entry {}: {}", i, e)))?;
+
+        step_best = step_best.max(ArrayView::from(&entry.embedding).dot(&hat_theta));
+
+        if entry.chosen {
+            cumulative_reward += entry.reward;
+            best_sum += step_best;
+            steps += 1;
+            step_best = f64::NEG_INFINITY;
+        }
+    }
+    if step_best > f64::NEG_INFINITY {
+        best_sum += step_best;
+        steps += 1;
+    }
+
+    let evaluation = PolicyEvaluation {
+        cumulative_reward,
+        mean_predicted_best: if steps == 0 { 0.0 } else { best_sum / steps as f64 },
+    };
+
+    Ok(serde_wasm_bindgen::to_value(&evaluation)?)
+}
+
+/// Uniform sample of `k` ids with Algorithm R, in reservoir order. `seed`
+/// makes the sample reproducible; when `k` is at least the number of ids,
+/// all of them are returned in input order.
+#[wasm_bindgen]
+pub fn reservoir_sample(article_ids_js: JsValue, k: usize, seed: u64) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let article_ids: Vec<String> = serde_wasm_bindgen::from_value(article_ids_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize article_ids: {}", e)))?;
+
+    let mut rng = rng::SplitMix64::new(seed);
+    let mut reservoir: Vec<String> = Vec::with_capacity(k.min(article_ids.len()));
+    for (i, id) in article_ids.into_iter().enumerate() {
+        if i < k {
+            reservoir.push(id);
+        } else {
+            // Keep item i with probability k / (i + 1)
+            let j = (rng.next_u64() % (i as u64 + 1)) as usize;
+            if j < k {
+                reservoir[j] = id;
+            }
+        }
+    }
+
+    Ok(serde_wasm_bindgen::to_value(&reservoir)?)
+}
+
+/// Returns a copy of `model` with `impressions` incremented, and `clicks` too
+/// when `clicked`. Missing counters start from 0.
+#[wasm_bindgen]
+pub fn record_impression(model_js: JsValue, clicked: bool) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let mut model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize BanditModel: {}", e)))?;
+
+    model.impressions = Some(model.impressions.unwrap_or(0).saturating_add(1));
+    let clicks = model.clicks.unwrap_or(0);
+    model.clicks = Some(if clicked { clicks.saturating_add(1) } else { clicks });
+
+    serde_wasm_bindgen::to_value(&model).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// `clicks / impressions` from the model's counters, or 0.0 when no
+/// impressions have been recorded.
+#[wasm_bindgen]
+pub fn get_ctr(model_js: JsValue) -> Result<f64, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize BanditModel: {}", e)))?;
+
+    Ok(model.stored_ctr().unwrap_or(0.0))
+}
+
+// Largest |a_ij - a_ji| tolerated before a model is flagged as drifted.
+const SYMMETRY_TOLERANCE: f64 = 1e-9;
+
+#[derive(Serialize, Deserialize)]
+pub struct ModelHealth {
+    #[serde(rename = "maxAsymmetry")]
+    pub max_asymmetry: f64,
+    #[serde(rename = "minEigenvalueEstimate")]
+    pub min_eigenvalue_estimate: f64,
+    #[serde(rename = "isSymmetric")]
+    pub is_symmetric: bool,
+}
+
+/// Reports numerical drift in A_inv. `minEigenvalueEstimate` is the smallest
+/// diagonal entry, which bounds the smallest eigenvalue from above, so a
+/// non-positive value means A_inv is no longer positive definite.
+#[wasm_bindgen]
+pub fn check_model_health(model_js: JsValue) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize BanditModel: {}", e)))?;
+
+    validate_bandit_model(&model).map_err(|e| JsValue::from_str(&e))?;
+
+    let d = model.dimension;
+    let a_inv = ArrayView2::from_shape((d, d), &model.a_inv)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let mut max_asymmetry: f64 = 0.0;
+    for i in 0..d {
+        for j in (i + 1)..d {
+            max_asymmetry = max_asymmetry.max((a_inv[[i, j]] - a_inv[[j, i]]).abs());
+        }
+    }
+
+    let min_eigenvalue_estimate = a_inv.diag().iter().cloned().fold(f64::INFINITY, f64::min);
+
+    let health = ModelHealth {
+        max_asymmetry,
+        min_eigenvalue_estimate,
+        is_symmetric: max_asymmetry <= SYMMETRY_TOLERANCE,
+    };
+
+    Ok(serde_wasm_bindgen::to_value(&health)?)
+}
+
+/// Cheap upper estimate of A_inv's condition number from Gershgorin discs:
+/// every eigenvalue lies in some `[a_ii - R_i, a_ii + R_i]` with `R_i` the
+/// off-diagonal absolute row sum, so `max(a_ii + R_i) / min(a_ii - R_i)` bounds
+/// `lambda_max / lambda_min`. Returns Infinity when the lower bound is not
+/// positive, i.e. when the discs cannot rule out a singular matrix.
+#[wasm_bindgen]
+pub fn get_condition_estimate(model_js: JsValue) -> Result<f64, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize BanditModel: {}", e)))?;
+
+    validate_bandit_model(&model).map_err(|e| JsValue::from_str(&e))?;
+
+    let d = model.dimension;
+    let a_inv = ArrayView2::from_shape((d, d), &model.a_inv)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let mut upper = f64::NEG_INFINITY;
+    let mut lower = f64::INFINITY;
+    for (i, row) in a_inv.outer_iter().enumerate() {
+        let center = row[i];
+        let radius: f64 = row.iter().map(|v| v.abs()).sum::<f64>() - center.abs();
+        upper = upper.max(center + radius);
+        lower = lower.min(center - radius);
+    }
+
+    if lower <= 0.0 {
+        return Ok(f64::INFINITY);
+    }
+
+    Ok(upper / lower)
+}
+
+/// Replaces A_inv with `(A_inv + A_inv^T) / 2` to undo accumulated
+/// floating-point asymmetry. `b` and `dimension` are left untouched.
+#[wasm_bindgen]
+pub fn symmetrize_model(model_js: JsValue) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let mut model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize BanditModel: {}", e)))?;
+
+    validate_bandit_model(&model).map_err(|e| JsValue::from_str(&e))?;
+
+    let d = model.dimension;
+    for i in 0..d {
+        for j in (i + 1)..d {
+            let avg = (model.a_inv[i * d + j] + model.a_inv[j * d + i]) / 2.0;
+            model.a_inv[i * d + j] = avg;
+            model.a_inv[j * d + i] = avg;
+        }
+    }
+
+    serde_wasm_bindgen::to_value(&model).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+// Binary model layout (little-endian):
+//   [0..4)  magic "LBM1"
+//   [4..8)  dimension as u32
+//   then d * d f64 values of a_inv followed by d f64 values of b
+const MODEL_BINARY_MAGIC: &[u8; 4] = b"LBM1";
+const MODEL_BINARY_HEADER_LEN: usize = 8;
+// Optional trailer: a presence-flags byte, then one u64 per counter that is
+// set, in the order impressions, clicks, update_count. Written only when at
+// least one counter is set, so its length is always 1 + 8k.
+const MODEL_BINARY_HAS_IMPRESSIONS: u8 = 1;
+const MODEL_BINARY_HAS_CLICKS: u8 = 1 << 1;
+const MODEL_BINARY_HAS_UPDATE_COUNT: u8 = 1 << 2;
+
+fn encode_model_binary(model: &BanditModel) -> Result<Vec<u8>, String> {
+    validate_bandit_model(model)?;
+    if model.shared.is_some() {
+        return Err("Binary format does not support hybrid models with a shared component.".to_string());
+    }
+    if model.multi.is_some() {
+        return Err("Binary format does not support multi-objective models.".to_string());
+    }
+
+    let dimension = u32::try_from(model.dimension)
+        .map_err(|_| "Bandit model dimension is too large to encode.".to_string())?;
+
+    let mut bytes = Vec::with_capacity(MODEL_BINARY_HEADER_LEN + 8 * (model.a_inv.len() + model.b.len()));
+    bytes.extend_from_slice(MODEL_BINARY_MAGIC);
+    bytes.extend_from_slice(&dimension.to_le_bytes());
+    for v in model.a_inv.iter().chain(model.b.iter()) {
+        bytes.extend_from_slice(&v.to_le_bytes());
+    }
+    let counters = [
+        (MODEL_BINARY_HAS_IMPRESSIONS, model.impressions),
+        (MODEL_BINARY_HAS_CLICKS, model.clicks),
+        (MODEL_BINARY_HAS_UPDATE_COUNT, model.update_count),
+    ];
+    let flags = counters.iter().filter(|(_, c)| c.is_some()).fold(0u8, |acc, (flag, _)| acc | flag);
+    if flags != 0 {
+        bytes.push(flags);
+        for value in counters.iter().filter_map(|(_, c)| *c) {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    Ok(bytes)
+}
+
+fn decode_model_binary(bytes: &[u8]) -> Result<BanditModel, String> {
+    if bytes.len() < MODEL_BINARY_HEADER_LEN || &bytes[0..4] != MODEL_BINARY_MAGIC {
+        return Err("Invalid binary model: missing header.".to_string());
+    }
+
+    let mut dim_bytes = [0u8; 4];
+    dim_bytes.copy_from_slice(&bytes[4..8]);
+    let d = u32::from_le_bytes(dim_bytes) as usize;
+
+    let expected = d
+        .checked_mul(d)
+        .and_then(|dd| dd.checked_add(d))
+        .and_then(|n| n.checked_mul(8))
+        .and_then(|n| n.checked_add(MODEL_BINARY_HEADER_LEN))
+        .ok_or_else(|| "Invalid binary model: dimension overflow.".to_string())?;
+    let length_error = || {
+        format!(
+            "Invalid binary model: expected {} bytes for dimension {}, got {}",
+            expected,
+            d,
+            bytes.len()
+        )
+    };
+    if bytes.len() < expected {
+        return Err(length_error());
+    }
+
+    let trailer = &bytes[expected..];
+    let read_u64 = |offset: usize| {
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(&trailer[offset..offset + 8]);
+        u64::from_le_bytes(buf)
+    };
+    let (impressions, clicks, update_count) = match trailer.len() {
+        0 => (None, None, None),
+        len => {
+            let flags = trailer[0];
+            let known = MODEL_BINARY_HAS_IMPRESSIONS | MODEL_BINARY_HAS_CLICKS | MODEL_BINARY_HAS_UPDATE_COUNT;
+            if flags == 0 || flags & !known != 0 {
+                return Err(format!("Invalid binary model: unknown counter flags {:#04x}", flags));
+            }
+            if len != 1 + 8 * flags.count_ones() as usize {
+                return Err(length_error());
+            }
+            let mut offset = 1;
+            let mut next = |flag: u8| {
+                (flags & flag != 0).then(|| {
+                    let value = read_u64(offset);
+                    offset += 8;
+                    value
+                })
+            };
+            let impressions = next(MODEL_BINARY_HAS_IMPRESSIONS);
+            let clicks = next(MODEL_BINARY_HAS_CLICKS);
+            (impressions, clicks, next(MODEL_BINARY_HAS_UPDATE_COUNT))
+        }
+    };
+
+    let mut values = bytes[MODEL_BINARY_HEADER_LEN..expected].chunks_exact(8).map(|chunk| {
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(chunk);
+        f64::from_le_bytes(buf)
+    });
+    let a_inv: Vec<f64> = values.by_ref().take(d * d).collect();
+    let b: Vec<f64> = values.collect();
+
+    let model = BanditModel { a_inv, b, dimension: d, shared: None, impressions, clicks, update_count, multi: None };
+    validate_bandit_model(&model)?;
+
+    Ok(model)
+}
+
+/// Encodes a model as a compact little-endian binary blob (`Uint8Array`).
+/// Round-trips exactly through `deserialize_model_binary`.
+#[wasm_bindgen]
+pub fn serialize_model_binary(model_js: JsValue) -> Result<Vec<u8>, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize BanditModel: {}", e)))?;
+
+    encode_model_binary(&model).map_err(|e| JsValue::from_str(&e))
+}
+
+#[wasm_bindgen]
+pub fn deserialize_model_binary(bytes: &[u8]) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let model = decode_model_binary(bytes).map_err(|e| JsValue::from_str(&e))?;
+
+    serde_wasm_bindgen::to_value(&model).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Base64 form of `serialize_model_binary`, for embedding in JSON documents.
+#[wasm_bindgen]
+pub fn model_to_base64(model_js: JsValue) -> Result<String, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize BanditModel: {}", e)))?;
+
+    let bytes = encode_model_binary(&model).map_err(|e| JsValue::from_str(&e))?;
+
+    Ok(base64::encode(&bytes))
+}
+
+#[wasm_bindgen]
+pub fn model_from_base64(s: &str) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let bytes = base64::decode(s).map_err(|e| JsValue::from_str(&e))?;
+    let model = decode_model_binary(&bytes).map_err(|e| JsValue::from_str(&e))?;
+
+    serde_wasm_bindgen::to_value(&model).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+// Appends `[v0,v1,...]` using Rust's shortest round-trip float formatting,
+// which is also valid JSON for every finite value.
+fn write_canonical_floats(out: &mut String, name: &str, values: &[f64]) -> Result<(), String> {
+    if values.iter().any(|v| !v.is_finite()) {
+        return Err(format!("Bandit model {} contains non-finite values (NaN/Inf).", name));
+    }
+    out.push('[');
+    for (i, v) in values.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push_str(&format!("{:?}", v));
+    }
+    out.push(']');
+    Ok(())
+}
+
+fn canonical_model_json(model: &BanditModel) -> Result<String, String> {
+    validate_bandit_model(model)?;
+
+    let mut out = String::with_capacity(24 * (model.a_inv.len() + model.b.len()) + 64);
+    out.push_str(&format!("{{\"dimension\":{},\"a_inv\":", model.dimension));
+    write_canonical_floats(&mut out, "A_inv", &model.a_inv)?;
+    out.push_str(",\"b\":");
+    write_canonical_floats(&mut out, "b", &model.b)?;
+    if let Some(impressions) = model.impressions {
+        out.push_str(&format!(",\"impressions\":{}", impressions));
+    }
+    if let Some(clicks) = model.clicks {
+        out.push_str(&format!(",\"clicks\":{}", clicks));
+    }
+    if let Some(update_count) = model.update_count {
+        out.push_str(&format!(",\"update_count\":{}", update_count));
+    }
+    if let Some(shared) = &model.shared {
+        out.push_str(&format!(",\"shared\":{{\"dimension\":{},\"a0_inv\":", shared.dimension));
+        write_canonical_floats(&mut out, "shared a0_inv", &shared.a0_inv)?;
+        out.push_str(",\"b0\":");
+        write_canonical_floats(&mut out, "shared b0", &shared.b0)?;
+        out.push_str(",\"b_cross\":");
+        write_canonical_floats(&mut out, "shared b_cross", &shared.b_cross)?;
+        out.push('}');
+    }
+    if let Some(multi) = &model.multi {
+        out.push_str(&format!(",\"multi\":{{\"objectives\":{},\"b\":", multi.objectives));
+        write_canonical_floats(&mut out, "multi b", &multi.b)?;
+        out.push('}');
+    }
+    out.push('}');
+
+    Ok(out)
+}
+
+/// Deterministic JSON for a model: `dimension`, `a_inv`, `b`, then the
+/// optional `impressions`, `clicks`, `update_count`, `shared` and `multi` fields, with floats in
+/// shortest round-trip form. Equal models always give identical strings, so
+/// the output can be diffed or hashed. `JSON.parse` gives back the model.
+#[wasm_bindgen]
+pub fn serialize_model_canonical(model_js: JsValue) -> Result<String, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize BanditModel: {}", e)))?;
+
+    canonical_model_json(&model).map_err(|e| JsValue::from_str(&e))
+}
+
+// Gauss-Jordan inversion with partial pivoting.
+fn invert_matrix(a: &Array2<f64>) -> Result<Array2<f64>, String> {
+    const EPS: f64 = 1e-12;
+    let d = a.nrows();
+    let mut m = a.clone();
+    let mut inv = Array2::<f64>::eye(d);
+
+    for col in 0..d {
+        let pivot = (col..d)
+            .max_by(|&i, &j| m[[i, col]].abs().total_cmp(&m[[j, col]].abs()))
+            .unwrap_or(col);
+        if !m[[pivot, col]].is_finite() || m[[pivot, col]].abs() < EPS {
+            return Err("Matrix is singular or ill-conditioned; cannot invert.".to_string());
+        }
+        if pivot != col {
+            for k in 0..d {
+                m.swap([pivot, k], [col, k]);
+                inv.swap([pivot, k], [col, k]);
+            }
+        }
+
+        let p = m[[col, col]];
+        for k in 0..d {
+            m[[col, k]] /= p;
+            inv[[col, k]] /= p;
+        }
+
+        for row in 0..d {
+            if row == col {
+                continue;
+            }
+            let factor = m[[row, col]];
+            if factor == 0.0 {
+                continue;
+            }
+            for k in 0..d {
+                m[[row, k]] -= factor * m[[col, k]];
+                inv[[row, k]] -= factor * inv[[col, k]];
+            }
+        }
+    }
+
+    Ok(inv)
+}
+
+// Sum of the counters that are present, or None when no model tracks them.
+fn sum_counters(counters: impl Iterator<Item = Option<u64>>) -> Option<u64> {
+    counters.fold(None, |acc, c| match (acc, c) {
+        (None, None) => None,
+        (acc, c) => Some(acc.unwrap_or(0).saturating_add(c.unwrap_or(0))),
+    })
+}
+
+/// Combines models trained on disjoint shards of traffic. Each shard's
+/// `A_i` is recovered from its `a_inv`, the data parts `A_i - lambda * I` are
+/// summed on top of a single `lambda * I` prior, and the result is inverted
+/// once. `b` vectors and CTR counters are summed. All shards must share
+/// `lambda` and dimension.
+#[wasm_bindgen]
+pub fn merge_models(models_js: JsValue, lambda: f64) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let models: Vec<BanditModel> = serde_wasm_bindgen::from_value(models_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize models: {}", e)))?;
+
+    if models.is_empty() {
+        return Err(JsValue::from_str("At least one model is required to merge."));
+    }
+    if !lambda.is_finite() || lambda <= 0.0 {
+        return Err(JsValue::from_str("lambda must be a positive finite number."));
+    }
+
+    let d = models[0].dimension;
+    for (i, model) in models.iter().enumerate() {
+        validate_bandit_model(model).map_err(|e| JsValue::from_str(&format!("Model {}: {}", i, e)))?;
+        if model.dimension != d {
+            return Err(JsValue::from_str(&format!(
+                "Model {} dimension mismatch: expected {}, got {}",
+                i, d, model.dimension
+            )));
+        }
+        if model.shared.is_some() {
+            return Err(JsValue::from_str(&format!(
+                "Model {} has a shared component; merging hybrid models is not supported.",
+                i
+            )));
+        }
+        if model.multi.is_some() {
+            return Err(JsValue::from_str(&format!(
+                "Model {} is multi-objective; merging multi-objective models is not supported.",
+                i
+            )));
+        }
+    }
+
+    let prior = Array2::<f64>::eye(d) * lambda;
+    let mut a_sum = prior.clone();
+    let mut b_sum = Array1::<f64>::zeros(d);
+
+    for (i, model) in models.iter().enumerate() {
+        let a_inv = ArrayView2::from_shape((d, d), &model.a_inv)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let a = invert_matrix(&a_inv.to_owned())
+            .map_err(|e| JsValue::from_str(&format!("Model {}: {}", i, e)))?;
+        a_sum += &(a - &prior);
+        b_sum += &ArrayView::from(&model.b);
+    }
+
+    let a_inv = invert_matrix(&a_sum).map_err(|e| JsValue::from_str(&format!("Merged A: {}", e)))?;
+
+    let merged = BanditModel {
+        a_inv: a_inv.into_raw_vec_and_offset().0,
+        b: b_sum.into_raw_vec_and_offset().0,
+        dimension: d,
+        shared: None,
+        impressions: sum_counters(models.iter().map(|m| m.impressions)),
+        clicks: sum_counters(models.iter().map(|m| m.clicks)),
+        update_count: sum_counters(models.iter().map(|m| m.update_count)),
+        multi: None,
+    };
+
+    serde_wasm_bindgen::to_value(&merged).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+// round(weight * a + (1 - weight) * b), with a missing counter counting as 0
+// as in `record_impression`. None only when both are missing. Rounding is
+// monotone, so blended clicks never exceed blended impressions.
+fn blend_counter(a: Option<u64>, b: Option<u64>, weight: f64) -> Option<u64> {
+    if a.is_none() && b.is_none() {
+        return None;
+    }
+    let blended = weight * a.unwrap_or(0) as f64 + (1.0 - weight) * b.unwrap_or(0) as f64;
+    Some(blended.round() as u64)
+}
+
+/// Convex blend of two models: `A = weight * A_a + (1 - weight) * A_b` (each
+/// rebuilt from its `a_inv`, the blend inverted once) and
+/// `b = weight * b_a + (1 - weight) * b_b`. Unlike `merge_models`, evidence is
+/// interpolated rather than added, and the counters are blended with the same
+/// weight (see `blend_counter`).
+#[wasm_bindgen]
+pub fn blend_models(model_a_js: JsValue, model_b_js: JsValue, weight: f64) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let model_a: BanditModel = serde_wasm_bindgen::from_value(model_a_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize model_a: {}", e)))?;
+    let model_b: BanditModel = serde_wasm_bindgen::from_value(model_b_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize model_b: {}", e)))?;
+
+    if !(0.0..=1.0).contains(&weight) {
+        return Err(JsValue::from_str("weight must be between 0 and 1."));
+    }
+    validate_bandit_model(&model_a).map_err(|e| JsValue::from_str(&format!("model_a: {}", e)))?;
+    validate_bandit_model(&model_b).map_err(|e| JsValue::from_str(&format!("model_b: {}", e)))?;
+    if model_a.dimension != model_b.dimension {
+        return Err(JsValue::from_str(&format!(
+            "Model dimension mismatch: {} vs {}",
+            model_a.dimension, model_b.dimension
+        )));
+    }
+    if model_a.shared.is_some() || model_b.shared.is_some() {
+        return Err(JsValue::from_str("Blending hybrid models with a shared component is not supported."));
+    }
+    if model_a.multi.is_some() || model_b.multi.is_some() {
+        return Err(JsValue::from_str("Blending multi-objective models is not supported."));
+    }
+
+    let d = model_a.dimension;
+    let recover_a = |model: &BanditModel, name: &str| -> Result<Array2<f64>, JsValue> {
+        let a_inv = ArrayView2::from_shape((d, d), &model.a_inv)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        invert_matrix(&a_inv.to_owned()).map_err(|e| JsValue::from_str(&format!("{}: {}", name, e)))
+    };
+    let a = recover_a(&model_a, "model_a")? * weight + recover_a(&model_b, "model_b")? * (1.0 - weight);
+    let a_inv = invert_matrix(&a).map_err(|e| JsValue::from_str(&format!("Blended A: {}", e)))?;
+
+    let b = &ArrayView::from(&model_a.b) * weight + &ArrayView::from(&model_b.b) * (1.0 - weight);
+
+    let blended = BanditModel {
+        a_inv: a_inv.into_raw_vec_and_offset().0,
+        b: b.into_raw_vec_and_offset().0,
+        dimension: d,
+        shared: None,
+        impressions: blend_counter(model_a.impressions, model_b.impressions, weight),
+        clicks: blend_counter(model_a.clicks, model_b.clicks, weight),
+        // Only meaningful when both models track it
+        update_count: model_a
+            .update_count
+            .zip(model_b.update_count)
+            .and_then(|(a, b)| blend_counter(Some(a), Some(b), weight)),
+        multi: None,
+    };
+
+    serde_wasm_bindgen::to_value(&blended).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Shrinks a model to the embedding dimensions in `keep_indices`, in the order
+/// given. A is recovered from `a_inv`, restricted to the kept rows/columns and
+/// re-inverted; together with `b` restricted the same way, this is exactly the
+/// model that training on the pruned embeddings would have produced.
+#[wasm_bindgen]
+pub fn truncate_model_dimension(model_js: JsValue, keep_indices_js: JsValue) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize BanditModel: {}", e)))?;
+    let keep: Vec<usize> = serde_wasm_bindgen::from_value(keep_indices_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize keep_indices: {}", e)))?;
+
+    validate_bandit_model(&model).map_err(|e| JsValue::from_str(&e))?;
+    if model.shared.is_some() {
+        return Err(JsValue::from_str(
+            "Model has a shared component; truncating hybrid models is not supported.",
+        ));
+    }
+    if model.multi.is_some() {
+        return Err(JsValue::from_str("Truncating multi-objective models is not supported."));
+    }
+
+    let d = model.dimension;
+    if keep.is_empty() {
+        return Err(JsValue::from_str("keep_indices must not be empty."));
+    }
+    let mut seen = vec![false; d];
+    for &i in &keep {
+        if i >= d {
+            return Err(JsValue::from_str(&format!(
+                "keep_indices entry {} is out of range for dimension {}",
+                i, d
+            )));
+        }
+        if seen[i] {
+            return Err(JsValue::from_str(&format!("keep_indices contains duplicate index {}", i)));
+        }
+        seen[i] = true;
+    }
+
+    let a_inv = ArrayView2::from_shape((d, d), &model.a_inv)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let a = invert_matrix(&a_inv.to_owned()).map_err(|e| JsValue::from_str(&e))?;
+
+    let new_d = keep.len();
+    let a_sub = Array2::from_shape_fn((new_d, new_d), |(r, c)| a[[keep[r], keep[c]]]);
+    let new_a_inv = invert_matrix(&a_sub).map_err(|e| JsValue::from_str(&format!("Truncated A: {}", e)))?;
+
+    let truncated = BanditModel {
+        a_inv: new_a_inv.into_raw_vec_and_offset().0,
+        b: keep.iter().map(|&i| model.b[i]).collect(),
+        dimension: new_d,
+        shared: None,
+        impressions: model.impressions,
+        clicks: model.clicks,
+        update_count: model.update_count,
+        multi: None,
+    };
+
+    serde_wasm_bindgen::to_value(&truncated).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ModelTrace {
+    #[serde(rename = "traceA")]
+    pub trace_a: f64,
+    #[serde(rename = "traceAInv")]
+    pub trace_a_inv: f64,
+}
+
+/// Traces of A (recovered by inverting A_inv) and of A_inv. trace(A) grows
+/// and trace(A_inv) shrinks as observations accumulate.
+#[wasm_bindgen]
+pub fn get_model_trace(model_js: JsValue) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize BanditModel: {}", e)))?;
+
+    validate_bandit_model(&model).map_err(|e| JsValue::from_str(&e))?;
+
+    let d = model.dimension;
+    let a_inv = Array2::from_shape_vec((d, d), model.a_inv)
+        .map_err(|e| JsValue::from_str(&format!("A_inv shape error: {}", e)))?;
+    let a = invert_matrix(&a_inv).map_err(|e| JsValue::from_str(&e))?;
+
+    let trace = ModelTrace {
+        trace_a: a.diag().sum(),
+        trace_a_inv: a_inv.diag().sum(),
+    };
+
+    Ok(serde_wasm_bindgen::to_value(&trace)?)
+}
+
+fn magnitude(vec: &[f64]) -> f64 {
+    vec.iter().map(|&a| a * a).sum::<f64>().sqrt()
+}
+
+// Final step of every cosine function, so they can't drift apart: a zero
+// magnitude gives 0.0, and rounding that lands just outside [-1, 1]
+// (e.g. 1.0000000002) is clamped.
+fn cosine_from_parts(dot_product: f64, magnitude1: f64, magnitude2: f64) -> f64 {
+    if magnitude1 == 0.0 || magnitude2 == 0.0 {
+        return 0.0; // Avoid division by zero, return 0 similarity for zero vectors
+    }
+
+    (dot_product / (magnitude1 * magnitude2)).clamp(-1.0, 1.0)
+}
+
+// Cosine similarity with the first vector's magnitude supplied by the caller,
+// so one-to-many loops compute it once. Mismatched or empty vectors give 0.0.
+fn cosine_with_magnitude(vec1: &[f64], magnitude1: f64, vec2: &[f64]) -> f64 {
+    if vec1.len() != vec2.len() || vec1.is_empty() {
+        return 0.0;
+    }
+
+    let (dot_product, magnitude2_sq) = simd::dot_and_norm_sq(vec1, vec2);
+
+    cosine_from_parts(dot_product, magnitude1, magnitude2_sq.sqrt())
+}
+
+fn cosine(vec1: &[f64], vec2: &[f64]) -> f64 {
+    cosine_with_magnitude(vec1, magnitude(vec1), vec2)
+}
+
+/// Copy of `model` with `N(0, sigma^2)` noise added to every element of `b`,
+/// for sharing models without exposing per-user rewards. With `perturb_a`,
+/// symmetric noise of the same scale is also added to A (recovered from A_inv)
+/// before re-inverting; large `sigma` can make A indefinite, so keep it small
+/// relative to the ridge `lambda`.
+#[wasm_bindgen]
+pub fn export_model_noisy(
+    model_js: JsValue,
+    sigma: f64,
+    seed: u64,
+    perturb_a: Option<bool>,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let mut model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize BanditModel: {}", e)))?;
+
+    if !sigma.is_finite() || sigma < 0.0 {
+        return Err(JsValue::from_str("sigma must be a non-negative finite number."));
+    }
+    validate_bandit_model(&model).map_err(|e| JsValue::from_str(&e))?;
+    // multi.b would leave the noisy export with the raw per-objective rewards
+    if model.multi.is_some() {
+        return Err(JsValue::from_str("Exporting multi-objective models with noise is not supported."));
+    }
+
+    let d = model.dimension;
+    let mut rng = rng::SplitMix64::new(seed);
+
+    for v in model.b.iter_mut() {
+        *v += sigma * rng.next_gaussian();
+    }
+
+    if perturb_a.unwrap_or(false) {
+        let a_inv = Array2::from_shape_vec((d, d), model.a_inv)
+            .map_err(|e| JsValue::from_str(&format!("A_inv shape error: {}", e)))?;
+        let mut a = invert_matrix(&a_inv).map_err(|e| JsValue::from_str(&e))?;
+        for i in 0..d {
+            for j in i..d {
+                let noise = sigma * rng.next_gaussian();
+                a[[i, j]] += noise;
+                if i != j {
+                    a[[j, i]] += noise;
+                }
+            }
+        }
+        let a_inv = invert_matrix(&a)
+            .map_err(|e| JsValue::from_str(&format!("Perturbed A could not be inverted: {}", e)))?;
+        model.a_inv = a_inv.into_raw_vec_and_offset().0;
+    }
+
+    serde_wasm_bindgen::to_value(&model).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+#[wasm_bindgen]
+pub fn cosine_similarity(
+    vec1_js: JsValue,
+    vec2_js: JsValue,
+) -> Result<f64, JsValue> {
+    utils::set_panic_hook();
+
+    let vec1: Vec<f64> = serde_wasm_bindgen::from_value(vec1_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vec1: {}", e)))?;
+    let vec2: Vec<f64> = serde_wasm_bindgen::from_value(vec2_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vec2: {}", e)))?;
+
+    if vec1.len() != vec2.len() {
+        return Err(JsValue::from_str("Vector dimensions mismatch."));
+    }
+    if vec1.is_empty() {
+        return Err(JsValue::from_str("Vectors cannot be empty."));
+    }
+
+    Ok(cosine(&vec1, &vec2))
+}
+
+#[wasm_bindgen]
+pub fn cosine_similarity_bulk(
+    vec1s_js: JsValue,
+    vec2s_js: JsValue,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let vec1s: Vec<Vec<f64>> = serde_wasm_bindgen::from_value(vec1s_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vec1s: {}", e)))?;
+    let vec2s: Vec<Vec<f64>> = serde_wasm_bindgen::from_value(vec2s_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vec2s: {}", e)))?;
+
+    if vec1s.len() != vec2s.len() {
+        return Err(JsValue::from_str("Input vector arrays must have the same length."));
+    }
+
+    Ok(serde_wasm_bindgen::to_value(&cosine_bulk(&vec1s, &vec2s))?)
+}
+
+// 次元が異なる場合・空のベクトル・ゼロベクトルはいずれも類似度0
+fn cosine_bulk(vec1s: &[Vec<f64>], vec2s: &[Vec<f64>]) -> Vec<f64> {
+    vec1s
+        .iter()
+        .zip(vec2s.iter())
+        .map(|(vec1, vec2)| cosine(vec1, vec2))
+        .collect()
+}
+
+#[wasm_bindgen]
+pub fn calculate_similarity_matrix(
+    vectors_js: JsValue,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let vectors: Vec<Vec<f64>> = serde_wasm_bindgen::from_value(vectors_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vectors: {}", e)))?;
+
+    Ok(serde_wasm_bindgen::to_value(&similarity_matrix(&vectors))?)
+}
+
+fn similarity_matrix(vectors: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    let n = vectors.len();
+    let mut similarity_matrix = vec![vec![0.0; n]; n];
+
+    for i in 0..n {
+        for j in i..n { // 対称行列なので、半分だけ計算してコピー
+            let similarity = cosine(&vectors[i], &vectors[j]);
+            similarity_matrix[i][j] = similarity;
+            similarity_matrix[j][i] = similarity; // 対称性を利用
+        }
+    }
+
+    similarity_matrix
+}
+
+#[wasm_bindgen]
+pub fn cosine_similarity_one_to_many(
+    target_vec_js: JsValue,
+    candidate_vecs_js: JsValue,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let target_vec: Vec<f64> = serde_wasm_bindgen::from_value(target_vec_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize target_vec: {}", e)))?;
+    let candidates: Vec<Vec<f64>> = serde_wasm_bindgen::from_value(candidate_vecs_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize candidate_vecs: {}", e)))?;
+
+    if target_vec.is_empty() {
+         return Err(JsValue::from_str("Target vector is empty."));
+    }
+
+    Ok(serde_wasm_bindgen::to_value(&cosine_one_to_many(&target_vec, &candidates))?)
+}
+
+fn cosine_one_to_many(target_vec: &[f64], candidates: &[Vec<f64>]) -> Vec<f64> {
+    // Pre-calculate target vector magnitude
+    let target_mag = magnitude(target_vec);
+
+    // Dimension mismatches and zero vectors yield 0
+    candidates
+        .iter()
+        .map(|candidate| cosine_with_magnitude(target_vec, target_mag, candidate))
+        .collect()
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ScoredArticle {
+    #[serde(rename = "articleId")]
+    pub article_id: String,
+    pub embedding: Vec<f64>,
+    pub score: f64,
+}
+
+// Greedy MMR selection over (score, embedding) pairs. Returns indices into
+// `candidates` in selection order.
+fn mmr_select(candidates: &[(f64, &[f64])], lambda: f64, k: usize) -> Vec<usize> {
+    let n = candidates.len();
+    let k = k.min(n);
+    let mut selected: Vec<usize> = Vec::with_capacity(k);
+    let mut remaining: Vec<usize> = (0..n).collect();
+    // Highest similarity of each candidate to anything already selected
+    let mut max_sim = vec![f64::NEG_INFINITY; n];
+
+    while selected.len() < k {
+        let mut best_pos = 0;
+        let mut best_value = f64::NEG_INFINITY;
+        for (pos, &i) in remaining.iter().enumerate() {
+            let redundancy = if selected.is_empty() { 0.0 } else { max_sim[i] };
+            let value = lambda * candidates[i].0 - (1.0 - lambda) * redundancy;
+            if value > best_value {
+                best_value = value;
+                best_pos = pos;
+            }
+        }
+
+        let chosen = remaining.remove(best_pos);
+        for &i in &remaining {
+            let sim = cosine(candidates[chosen].1, candidates[i].1);
+            if sim > max_sim[i] {
+                max_sim[i] = sim;
+            }
+        }
+        selected.push(chosen);
+    }
+
+    selected
+}
+
+fn validate_mmr_lambda(lambda: f64) -> Result<(), JsValue> {
+    if !lambda.is_finite() || !(0.0..=1.0).contains(&lambda) {
+        return Err(JsValue::from_str("lambda must be within [0, 1]."));
+    }
+    Ok(())
+}
+
+/// Maximal Marginal Relevance re-ranking. Greedily picks `k` articles that
+/// maximize `lambda * score - (1 - lambda) * max_sim_to_selected` and returns
+/// their ids in selection order.
+#[wasm_bindgen]
+pub fn rerank_mmr(
+    scored_articles_js: JsValue,
+    lambda: f64,
+    k: usize,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let articles: Vec<ScoredArticle> = serde_wasm_bindgen::from_value(scored_articles_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize scored articles: {}", e)))?;
+
+    validate_mmr_lambda(lambda)?;
+
+    let candidates: Vec<(f64, &[f64])> = articles
+        .iter()
+        .map(|a| (a.score, a.embedding.as_slice()))
+        .collect();
+    let selected: Vec<&str> = mmr_select(&candidates, lambda, k)
+        .into_iter()
+        .map(|i| articles[i].article_id.as_str())
+        .collect();
+
+    Ok(serde_wasm_bindgen::to_value(&selected)?)
+}
+
+/// One-call recommendation pipeline: UCB scoring followed by MMR
+/// diversification, returning the final `k` article ids.
+#[wasm_bindgen]
+pub fn recommend(
+    model_js: JsValue,
+    articles_js: JsValue,
+    user_ctr: f64,
+    k: usize,
+    mmr_lambda: f64,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let articles: Vec<Article> = serde_wasm_bindgen::from_value(articles_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    validate_mmr_lambda(mmr_lambda)?;
+
+    if articles.is_empty() {
+        return Ok(serde_wasm_bindgen::to_value(&Vec::<String>::new())?);
+    }
+
+    let scorer = UcbScorer::new(&model, user_ctr, &UcbOptions::default())?;
+
+    let mut scored: Vec<(f64, &Article)> = Vec::with_capacity(articles.len());
+    for article in &articles {
+        if let Some((term1, term2)) = scorer.score(&article.article_id, &article.embedding)? {
+            scored.push((term1 + term2, article));
+        }
+    }
+
+    let candidates: Vec<(f64, &[f64])> = scored
+        .iter()
+        .map(|(ucb, a)| (*ucb, a.embedding.as_slice()))
+        .collect();
+    let selected: Vec<&str> = mmr_select(&candidates, mmr_lambda, k)
+        .into_iter()
+        .map(|i| scored[i].1.article_id.as_str())
+        .collect();
+
+    Ok(serde_wasm_bindgen::to_value(&selected)?)
+}
+
+#[wasm_bindgen]
+pub fn euclidean_distance(
+    vec1_js: JsValue,
+    vec2_js: JsValue,
+) -> Result<f64, JsValue> {
+    utils::set_panic_hook();
+
+    let vec1: Vec<f64> = serde_wasm_bindgen::from_value(vec1_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vec1: {}", e)))?;
+    let vec2: Vec<f64> = serde_wasm_bindgen::from_value(vec2_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vec2: {}", e)))?;
+
+    if vec1.len() != vec2.len() {
+        return Err(JsValue::from_str("Vector dimensions mismatch."));
+    }
+    if vec1.is_empty() {
+        return Err(JsValue::from_str("Vectors cannot be empty."));
+    }
+
+    Ok(squared_distance(&vec1, &vec2).sqrt())
+}
+
+#[wasm_bindgen]
+pub fn euclidean_distance_one_to_many(
+    target_vec_js: JsValue,
+    candidate_vecs_js: JsValue,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let target_vec: Vec<f64> = serde_wasm_bindgen::from_value(target_vec_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize target_vec: {}", e)))?;
+    let candidates: Vec<Vec<f64>> = serde_wasm_bindgen::from_value(candidate_vecs_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize candidate_vecs: {}", e)))?;
+
+    if target_vec.is_empty() {
+        return Err(JsValue::from_str("Target vector is empty."));
+    }
+
+    let mut results = Vec::with_capacity(candidates.len());
+
+    for candidate in candidates {
+        if candidate.len() != target_vec.len() {
+            results.push(f64::INFINITY); // Dimension mismatch
+            continue;
+        }
+
+        results.push(squared_distance(&target_vec, &candidate).sqrt());
+    }
+
+    Ok(serde_wasm_bindgen::to_value(&results)?)
+}
+
+/// Raw dot products between `target` and each candidate. No magnitude
+/// normalization is done, so this equals cosine similarity only when callers
+/// have already L2-normalized every embedding.
+#[wasm_bindgen]
+pub fn dot_product_one_to_many(
+    target_vec_js: JsValue,
+    candidate_vecs_js: JsValue,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let target_vec: Vec<f64> = serde_wasm_bindgen::from_value(target_vec_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize target_vec: {}", e)))?;
+    let candidates: Vec<Vec<f64>> = serde_wasm_bindgen::from_value(candidate_vecs_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize candidate_vecs: {}", e)))?;
+
+    if target_vec.is_empty() {
+        return Err(JsValue::from_str("Target vector is empty."));
+    }
+
+    let mut results = Vec::with_capacity(candidates.len());
+
+    for candidate in candidates {
+        if candidate.len() != target_vec.len() {
+            results.push(0.0); // Dimension mismatch
+            continue;
+        }
+
+        results.push(simd::dot(&target_vec, &candidate));
+    }
+
+    Ok(serde_wasm_bindgen::to_value(&results)?)
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct IndexedScore {
+    pub index: usize,
+    pub score: f64,
+}
+
+/// Like `cosine_similarity_one_to_many`, but returns `{ index, score }` pairs
+/// sorted by descending score. Candidates whose dimension differs from the
+/// target are left out instead of being reported as 0.0.
+#[wasm_bindgen]
+pub fn cosine_similarity_one_to_many_indexed(
+    target_vec_js: JsValue,
+    candidate_vecs_js: JsValue,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let target_vec: Vec<f64> = serde_wasm_bindgen::from_value(target_vec_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize target_vec: {}", e)))?;
+    let candidates: Vec<Vec<f64>> = serde_wasm_bindgen::from_value(candidate_vecs_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize candidate_vecs: {}", e)))?;
+
+    if target_vec.is_empty() {
+        return Err(JsValue::from_str("Target vector is empty."));
+    }
+
+    let mut results: Vec<IndexedScore> = candidates
+        .iter()
+        .enumerate()
+        .filter(|(_, candidate)| candidate.len() == target_vec.len())
+        .map(|(index, candidate)| IndexedScore {
+            index,
+            score: cosine(&target_vec, candidate),
+        })
+        .collect();
+
+    // Stable sort keeps equal scores in input order
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    Ok(serde_wasm_bindgen::to_value(&results)?)
+}
+
+// Divides the vector by its L2 norm in place. Zero vectors are left as-is.
+fn l2_normalize(vec: &mut [f64]) {
+    let norm = magnitude(vec);
+    if norm == 0.0 {
+        return;
+    }
+    for v in vec.iter_mut() {
+        *v /= norm;
+    }
+}
+
+#[wasm_bindgen]
+pub fn normalize_vector(vec_js: JsValue) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let mut vec: Vec<f64> = serde_wasm_bindgen::from_value(vec_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vec: {}", e)))?;
+
+    l2_normalize(&mut vec);
+
+    Ok(serde_wasm_bindgen::to_value(&vec)?)
+}
+
+#[wasm_bindgen]
+pub fn normalize_vectors(vectors_js: JsValue) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let mut vectors: Vec<Vec<f64>> = serde_wasm_bindgen::from_value(vectors_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vectors: {}", e)))?;
+
+    for vec in vectors.iter_mut() {
+        l2_normalize(vec);
+    }
+
+    Ok(serde_wasm_bindgen::to_value(&vectors)?)
+}
+
+// L1 distance sum |a_i - b_i|.
+fn manhattan(vec1: &[f64], vec2: &[f64]) -> f64 {
+    vec1.iter().zip(vec2.iter()).map(|(&a, &b)| (a - b).abs()).sum()
+}
+
+#[wasm_bindgen]
+pub fn manhattan_distance(
+    vec1_js: JsValue,
+    vec2_js: JsValue,
+) -> Result<f64, JsValue> {
+    utils::set_panic_hook();
+
+    let vec1: Vec<f64> = serde_wasm_bindgen::from_value(vec1_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vec1: {}", e)))?;
+    let vec2: Vec<f64> = serde_wasm_bindgen::from_value(vec2_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vec2: {}", e)))?;
+
+    if vec1.len() != vec2.len() {
+        return Err(JsValue::from_str("Vector dimensions mismatch."));
+    }
+    if vec1.is_empty() {
+        return Err(JsValue::from_str("Vectors cannot be empty."));
+    }
+
+    Ok(manhattan(&vec1, &vec2))
+}
+
+#[wasm_bindgen]
+pub fn manhattan_distance_one_to_many(
+    target_vec_js: JsValue,
+    candidate_vecs_js: JsValue,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let target_vec: Vec<f64> = serde_wasm_bindgen::from_value(target_vec_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize target_vec: {}", e)))?;
+    let candidates: Vec<Vec<f64>> = serde_wasm_bindgen::from_value(candidate_vecs_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize candidate_vecs: {}", e)))?;
+
+    if target_vec.is_empty() {
+        return Err(JsValue::from_str("Target vector is empty."));
+    }
+
+    let mut results = Vec::with_capacity(candidates.len());
+
+    for candidate in candidates {
+        if candidate.len() != target_vec.len() {
+            results.push(f64::INFINITY); // Dimension mismatch
+            continue;
+        }
+
+        results.push(manhattan(&target_vec, &candidate));
+    }
+
+    Ok(serde_wasm_bindgen::to_value(&results)?)
+}
+
+/// Row-major `n x n` similarity matrix. `data` is handed to JS as a
+/// `Float64Array`, so entry (i, j) is `data[i * n + j]`.
+#[wasm_bindgen]
+pub struct FlatMatrix {
+    data: Vec<f64>,
+    n: usize,
+}
+
+#[wasm_bindgen]
+impl FlatMatrix {
+    #[wasm_bindgen(getter)]
+    pub fn data(&self) -> Vec<f64> {
+        self.data.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn n(&self) -> usize {
+        self.n
+    }
+}
+
+/// Flat variant of `calculate_similarity_matrix` that avoids allocating `n`
+/// nested JS arrays.
+#[wasm_bindgen]
+pub fn calculate_similarity_matrix_flat(
+    vectors_js: JsValue,
+) -> Result<FlatMatrix, JsValue> {
+    utils::set_panic_hook();
+
+    let vectors: Vec<Vec<f64>> = serde_wasm_bindgen::from_value(vectors_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vectors: {}", e)))?;
+
+    let n = vectors.len();
+    let mut data = vec![0.0; n * n];
+
+    for i in 0..n {
+        for j in i..n { // 対称行列なので、半分だけ計算してコピー
+            let similarity = cosine(&vectors[i], &vectors[j]);
+            data[i * n + j] = similarity;
+            data[j * n + i] = similarity;
+        }
+    }
+
+    Ok(FlatMatrix { data, n })
+}
+
+/// `calculate_similarity_matrix_flat` with row labels: row and column `i`
+/// both belong to `ids[i]`, and entry (i, j) is `matrix[i * n + j]`.
+#[wasm_bindgen]
+pub struct LabeledMatrix {
+    ids: Vec<String>,
+    matrix: Vec<f64>,
+}
+
+#[wasm_bindgen]
+impl LabeledMatrix {
+    #[wasm_bindgen(getter)]
+    pub fn ids(&self) -> Vec<String> {
+        self.ids.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn matrix(&self) -> Vec<f64> {
+        self.matrix.clone()
+    }
+}
+
+#[wasm_bindgen]
+pub fn calculate_similarity_matrix_labeled(
+    articles_js: JsValue,
+) -> Result<LabeledMatrix, JsValue> {
+    utils::set_panic_hook();
+
+    let articles: Vec<Article> = serde_wasm_bindgen::from_value(articles_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize articles: {}", e)))?;
+
+    let n = articles.len();
+    let mut matrix = vec![0.0; n * n];
+
+    for i in 0..n {
+        for j in i..n { // 対称行列なので、半分だけ計算してコピー
+            let similarity = cosine(&articles[i].embedding, &articles[j].embedding);
+            matrix[i * n + j] = similarity;
+            matrix[j * n + i] = similarity;
+        }
+    }
+
+    let ids = articles.into_iter().map(|a| a.article_id).collect();
+
+    Ok(LabeledMatrix { ids, matrix })
+}
+
+/// Gram matrix of raw dot products `v_i · v_j`, in the same flat layout as
+/// `calculate_similarity_matrix_flat`. Unlike the cosine matrix it keeps
+/// magnitudes. Pairs with different dimensions get 0.0.
+#[wasm_bindgen]
+pub fn gram_matrix(
+    vectors_js: JsValue,
+) -> Result<FlatMatrix, JsValue> {
+    utils::set_panic_hook();
+
+    let vectors: Vec<Vec<f64>> = serde_wasm_bindgen::from_value(vectors_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vectors: {}", e)))?;
+
+    let n = vectors.len();
+    let mut data = vec![0.0; n * n];
+
+    for i in 0..n {
+        for j in i..n { // symmetric, so compute the upper half and mirror it
+            if vectors[i].len() != vectors[j].len() {
+                continue;
+            }
+            let dot = simd::dot(&vectors[i], &vectors[j]);
+            data[i * n + j] = dot;
+            data[j * n + i] = dot;
+        }
+    }
+
+    Ok(FlatMatrix { data, n })
+}
+
+/// Upper triangle (diagonal included) of the cosine similarity matrix, as a
+/// `Float64Array` of length `n * (n + 1) / 2` in row-major order. For
+/// `i <= j`, entry (i, j) lives at `i * (2 * n - i + 1) / 2 + (j - i)`; the
+/// lower half follows from symmetry.
+#[wasm_bindgen]
+pub fn calculate_similarity_matrix_upper(
+    vectors_js: JsValue,
+) -> Result<Vec<f64>, JsValue> {
+    utils::set_panic_hook();
+
+    let vectors: Vec<Vec<f64>> = serde_wasm_bindgen::from_value(vectors_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vectors: {}", e)))?;
+
+    let n = vectors.len();
+    let mut data = Vec::with_capacity(n * (n + 1) / 2);
+
+    for i in 0..n {
+        for j in i..n {
+            data.push(cosine(&vectors[i], &vectors[j]));
+        }
+    }
+
+    Ok(data)
+}
+
+#[wasm_bindgen]
+pub fn precompute_magnitudes(vectors_js: JsValue) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let vectors: Vec<Vec<f64>> = serde_wasm_bindgen::from_value(vectors_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vectors: {}", e)))?;
+
+    let magnitudes: Vec<f64> = vectors
+        .iter()
+        .map(|v| magnitude(v))
+        .collect();
+
+    Ok(serde_wasm_bindgen::to_value(&magnitudes)?)
+}
+
+/// `cosine_similarity_one_to_many` using candidate magnitudes from
+/// `precompute_magnitudes`, for candidate pools reused across many targets.
+#[wasm_bindgen]
+pub fn cosine_similarity_one_to_many_cached(
+    target_vec_js: JsValue,
+    candidate_vecs_js: JsValue,
+    magnitudes_js: JsValue,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let target_vec: Vec<f64> = serde_wasm_bindgen::from_value(target_vec_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize target_vec: {}", e)))?;
+    let candidates: Vec<Vec<f64>> = serde_wasm_bindgen::from_value(candidate_vecs_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize candidate_vecs: {}", e)))?;
+    let magnitudes: Vec<f64> = serde_wasm_bindgen::from_value(magnitudes_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize magnitudes: {}", e)))?;
+
+    if target_vec.is_empty() {
+        return Err(JsValue::from_str("Target vector is empty."));
+    }
+    if magnitudes.len() != candidates.len() {
+        return Err(JsValue::from_str(&format!(
+            "Magnitudes length mismatch: expected {}, got {}",
+            candidates.len(),
+            magnitudes.len()
+        )));
+    }
+
+    let target_mag = magnitude(&target_vec);
+
+    if target_mag == 0.0 {
+        return Ok(serde_wasm_bindgen::to_value(&vec![0.0; candidates.len()])?);
+    }
+
+    let mut results = Vec::with_capacity(candidates.len());
+
+    for (candidate, &cand_mag) in candidates.iter().zip(magnitudes.iter()) {
+        if candidate.len() != target_vec.len() {
+            results.push(0.0); // Dimension mismatch
+            continue;
+        }
+
+        results.push(cosine_from_parts(simd::dot(&target_vec, candidate), target_mag, cand_mag));
+    }
+
+    Ok(serde_wasm_bindgen::to_value(&results)?)
+}
+
+/// Grows a flat similarity matrix (as returned by
+/// `calculate_similarity_matrix_flat`) with `new_vectors`, computing only the
+/// new rows/columns. The result covers `existing_vectors` followed by
+/// `new_vectors`.
+#[wasm_bindgen]
+pub fn extend_similarity_matrix(
+    existing_matrix_js: JsValue,
+    existing_vectors_js: JsValue,
+    new_vectors_js: JsValue,
+) -> Result<FlatMatrix, JsValue> {
+    utils::set_panic_hook();
+
+    let existing_matrix: Vec<f64> = serde_wasm_bindgen::from_value(existing_matrix_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize existing_matrix: {}", e)))?;
+    let existing_vectors: Vec<Vec<f64>> = serde_wasm_bindgen::from_value(existing_vectors_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize existing_vectors: {}", e)))?;
+    let new_vectors: Vec<Vec<f64>> = serde_wasm_bindgen::from_value(new_vectors_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize new_vectors: {}", e)))?;
+
+    let old_n = existing_vectors.len();
+    if existing_matrix.len() != old_n * old_n {
+        return Err(JsValue::from_str(&format!(
+            "Existing matrix length mismatch: expected {} for {} vectors, got {}",
+            old_n * old_n,
+            old_n,
+            existing_matrix.len()
+        )));
+    }
+
+    let n = old_n + new_vectors.len();
+    let mut data = vec![0.0; n * n];
+
+    // Copy the existing block row by row into the wider layout
+    for i in 0..old_n {
+        data[i * n..i * n + old_n].copy_from_slice(&existing_matrix[i * old_n..(i + 1) * old_n]);
+    }
+
+    let vector_at = |i: usize| -> &Vec<f64> {
+        if i < old_n { &existing_vectors[i] } else { &new_vectors[i - old_n] }
+    };
+
+    for i in old_n..n {
+        for j in 0..=i {
+            let similarity = cosine(vector_at(i), vector_at(j));
+            data[i * n + j] = similarity;
+            data[j * n + i] = similarity;
+        }
+    }
+
+    Ok(FlatMatrix { data, n })
+}
+
+/// Cosine similarity after scaling dimension `i` by `sqrt(weights[i])`:
+/// `sum(w * a * b) / (sqrt(sum(w * a^2)) * sqrt(sum(w * b^2)))`.
+#[wasm_bindgen]
+pub fn weighted_cosine_similarity(
+    vec1_js: JsValue,
+    vec2_js: JsValue,
+    weights_js: JsValue,
+) -> Result<f64, JsValue> {
+    utils::set_panic_hook();
+
+    let vec1: Vec<f64> = serde_wasm_bindgen::from_value(vec1_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vec1: {}", e)))?;
+    let vec2: Vec<f64> = serde_wasm_bindgen::from_value(vec2_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vec2: {}", e)))?;
+    let weights: Vec<f64> = serde_wasm_bindgen::from_value(weights_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize weights: {}", e)))?;
+
+    if vec1.len() != vec2.len() || vec1.len() != weights.len() {
+        return Err(JsValue::from_str("Vector and weight dimensions mismatch."));
+    }
+    if vec1.is_empty() {
+        return Err(JsValue::from_str("Vectors cannot be empty."));
+    }
+    if weights.iter().any(|&w| !w.is_finite() || w < 0.0) {
+        return Err(JsValue::from_str("Weights must be finite and non-negative."));
+    }
+
+    let mut dot_product = 0.0;
+    let mut mag1_sq = 0.0;
+    let mut mag2_sq = 0.0;
+
+    for ((&a, &b), &w) in vec1.iter().zip(vec2.iter()).zip(weights.iter()) {
+        dot_product += w * a * b;
+        mag1_sq += w * a * a;
+        mag2_sq += w * b * b;
+    }
+
+    Ok(cosine_from_parts(dot_product, mag1_sq.sqrt(), mag2_sq.sqrt()))
+}
+
+// Nonzero entries are treated as set membership. An empty union gives 0.0.
+fn jaccard(vec1: &[f64], vec2: &[f64]) -> f64 {
+    let mut intersection = 0usize;
+    let mut union = 0usize;
+
+    for (&a, &b) in vec1.iter().zip(vec2.iter()) {
+        let in1 = a != 0.0;
+        let in2 = b != 0.0;
+        if in1 && in2 {
+            intersection += 1;
+        }
+        if in1 || in2 {
+            union += 1;
+        }
+    }
+
+    if union == 0 {
+        return 0.0;
+    }
+
+    intersection as f64 / union as f64
+}
+
+#[wasm_bindgen]
+pub fn jaccard_similarity(
+    vec1_js: JsValue,
+    vec2_js: JsValue,
+) -> Result<f64, JsValue> {
+    utils::set_panic_hook();
+
+    let vec1: Vec<f64> = serde_wasm_bindgen::from_value(vec1_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vec1: {}", e)))?;
+    let vec2: Vec<f64> = serde_wasm_bindgen::from_value(vec2_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vec2: {}", e)))?;
+
+    if vec1.len() != vec2.len() {
+        return Err(JsValue::from_str("Vector dimensions mismatch."));
+    }
+    if vec1.is_empty() {
+        return Err(JsValue::from_str("Vectors cannot be empty."));
+    }
+
+    Ok(jaccard(&vec1, &vec2))
+}
+
+#[wasm_bindgen]
+pub fn jaccard_similarity_one_to_many(
+    target_vec_js: JsValue,
+    candidate_vecs_js: JsValue,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let target_vec: Vec<f64> = serde_wasm_bindgen::from_value(target_vec_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize target_vec: {}", e)))?;
+    let candidates: Vec<Vec<f64>> = serde_wasm_bindgen::from_value(candidate_vecs_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize candidate_vecs: {}", e)))?;
+
+    if target_vec.is_empty() {
+        return Err(JsValue::from_str("Target vector is empty."));
+    }
+
+    let mut results = Vec::with_capacity(candidates.len());
+
+    for candidate in candidates {
+        if candidate.len() != target_vec.len() {
+            results.push(0.0); // Dimension mismatch
+            continue;
+        }
+        results.push(jaccard(&target_vec, &candidate));
+    }
+
+    Ok(serde_wasm_bindgen::to_value(&results)?)
+}
+
+// Cosine similarity of the mean-centered vectors. Constant vectors have zero
+// variance and give 0.0 instead of NaN.
+fn pearson(vec1: &[f64], vec2: &[f64]) -> f64 {
+    let n = vec1.len() as f64;
+    let mean1 = vec1.iter().sum::<f64>() / n;
+    let mean2 = vec2.iter().sum::<f64>() / n;
+
+    let mut cov = 0.0;
+    let mut var1 = 0.0;
+    let mut var2 = 0.0;
+
+    for (&a, &b) in vec1.iter().zip(vec2.iter()) {
+        let da = a - mean1;
+        let db = b - mean2;
+        cov += da * db;
+        var1 += da * da;
+        var2 += db * db;
+    }
+
+    if var1 == 0.0 || var2 == 0.0 {
+        return 0.0;
+    }
+
+    cov / (var1.sqrt() * var2.sqrt())
+}
+
+#[wasm_bindgen]
+pub fn pearson_correlation(
+    vec1_js: JsValue,
+    vec2_js: JsValue,
+) -> Result<f64, JsValue> {
+    utils::set_panic_hook();
+
+    let vec1: Vec<f64> = serde_wasm_bindgen::from_value(vec1_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vec1: {}", e)))?;
+    let vec2: Vec<f64> = serde_wasm_bindgen::from_value(vec2_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vec2: {}", e)))?;
+
+    if vec1.len() != vec2.len() {
+        return Err(JsValue::from_str("Vector dimensions mismatch."));
+    }
+    if vec1.is_empty() {
+        return Err(JsValue::from_str("Vectors cannot be empty."));
+    }
+
+    Ok(pearson(&vec1, &vec2))
+}
+
+#[wasm_bindgen]
+pub fn pearson_correlation_one_to_many(
+    target_vec_js: JsValue,
+    candidate_vecs_js: JsValue,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let target_vec: Vec<f64> = serde_wasm_bindgen::from_value(target_vec_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize target_vec: {}", e)))?;
+    let candidates: Vec<Vec<f64>> = serde_wasm_bindgen::from_value(candidate_vecs_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize candidate_vecs: {}", e)))?;
+
+    if target_vec.is_empty() {
+        return Err(JsValue::from_str("Target vector is empty."));
+    }
+
+    let mut results = Vec::with_capacity(candidates.len());
+
+    for candidate in candidates {
+        if candidate.len() != target_vec.len() {
+            results.push(0.0); // Dimension mismatch
+            continue;
+        }
+        results.push(pearson(&target_vec, &candidate));
+    }
+
+    Ok(serde_wasm_bindgen::to_value(&results)?)
+}
+
+// acos(cosine) / pi in [0, 1]. cosine_from_parts already clamps into [-1, 1],
+// so acos never sees a rounded value outside its domain.
+fn angular(vec1: &[f64], vec2: &[f64]) -> f64 {
+    cosine(vec1, vec2).acos() / std::f64::consts::PI
+}
+
+#[wasm_bindgen]
+pub fn angular_distance(
+    vec1_js: JsValue,
+    vec2_js: JsValue,
+) -> Result<f64, JsValue> {
+    utils::set_panic_hook();
+
+    let vec1: Vec<f64> = serde_wasm_bindgen::from_value(vec1_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vec1: {}", e)))?;
+    let vec2: Vec<f64> = serde_wasm_bindgen::from_value(vec2_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vec2: {}", e)))?;
+
+    if vec1.len() != vec2.len() {
+        return Err(JsValue::from_str("Vector dimensions mismatch."));
+    }
+    if vec1.is_empty() {
+        return Err(JsValue::from_str("Vectors cannot be empty."));
+    }
+
+    Ok(angular(&vec1, &vec2))
+}
+
+#[wasm_bindgen]
+pub fn angular_distance_one_to_many(
+    target_vec_js: JsValue,
+    candidate_vecs_js: JsValue,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let target_vec: Vec<f64> = serde_wasm_bindgen::from_value(target_vec_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize target_vec: {}", e)))?;
+    let candidates: Vec<Vec<f64>> = serde_wasm_bindgen::from_value(candidate_vecs_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize candidate_vecs: {}", e)))?;
+
+    if target_vec.is_empty() {
+        return Err(JsValue::from_str("Target vector is empty."));
+    }
+
+    let mut results = Vec::with_capacity(candidates.len());
+
+    for candidate in candidates {
+        if candidate.len() != target_vec.len() {
+            results.push(f64::INFINITY); // Dimension mismatch
+            continue;
+        }
+        results.push(angular(&target_vec, &candidate));
+    }
+
+    Ok(serde_wasm_bindgen::to_value(&results)?)
+}
+
+// Neumaier's variant of Kahan summation, which also stays accurate when a
+// new term is larger than the running sum.
+#[derive(Default)]
+struct CompensatedSum {
+    sum: f64,
+    compensation: f64,
+}
+
+impl CompensatedSum {
+    fn add(&mut self, value: f64) {
+        let t = self.sum + value;
+        if self.sum.abs() >= value.abs() {
+            self.compensation += (self.sum - t) + value;
+        } else {
+            self.compensation += (value - t) + self.sum;
+        }
+        self.sum = t;
+    }
+
+    fn total(&self) -> f64 {
+        self.sum + self.compensation
+    }
+}
+
+/// `cosine_similarity` with compensated summation for the dot product and
+/// magnitudes. Slower, but avoids the precision loss of naive summation on
+/// very high-dimensional vectors.
+#[wasm_bindgen]
+pub fn cosine_similarity_precise(
+    vec1_js: JsValue,
+    vec2_js: JsValue,
+) -> Result<f64, JsValue> {
+    utils::set_panic_hook();
+
+    let vec1: Vec<f64> = serde_wasm_bindgen::from_value(vec1_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vec1: {}", e)))?;
+    let vec2: Vec<f64> = serde_wasm_bindgen::from_value(vec2_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vec2: {}", e)))?;
+
+    if vec1.len() != vec2.len() {
+        return Err(JsValue::from_str("Vector dimensions mismatch."));
+    }
+    if vec1.is_empty() {
+        return Err(JsValue::from_str("Vectors cannot be empty."));
+    }
+
+    let mut dot_product = CompensatedSum::default();
+    let mut mag1_sq = CompensatedSum::default();
+    let mut mag2_sq = CompensatedSum::default();
+
+    for (&a, &b) in vec1.iter().zip(vec2.iter()) {
+        dot_product.add(a * b);
+        mag1_sq.add(a * a);
+        mag2_sq.add(b * b);
+    }
+
+    let magnitude1 = mag1_sq.total().sqrt();
+    let magnitude2 = mag2_sq.total().sqrt();
+
+    Ok(cosine_from_parts(dot_product.total(), magnitude1, magnitude2))
+}
+
+/// `cosine_similarity_one_to_many` over candidates packed row-major into one
+/// `Float64Array` of `n * row_dim` values. Returns a `Float64Array` of `n`
+/// similarities.
+#[wasm_bindgen]
+pub fn cosine_similarity_one_to_many_flat(
+    target_vec_js: JsValue,
+    candidates_flat: &[f64],
+    row_dim: usize,
+) -> Result<Vec<f64>, JsValue> {
+    utils::set_panic_hook();
+
+    let target_vec: Vec<f64> = serde_wasm_bindgen::from_value(target_vec_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize target_vec: {}", e)))?;
+
+    if target_vec.is_empty() {
+        return Err(JsValue::from_str("Target vector is empty."));
+    }
+    if row_dim == 0 {
+        return Err(JsValue::from_str("row_dim cannot be zero."));
+    }
+    if !candidates_flat.len().is_multiple_of(row_dim) {
+        return Err(JsValue::from_str(&format!(
+            "Flat buffer length {} is not a multiple of row_dim {}.",
+            candidates_flat.len(),
+            row_dim
+        )));
+    }
+    if row_dim != target_vec.len() {
+        return Err(JsValue::from_str(&format!(
+            "row_dim {} does not match target vector dimension {}.",
+            row_dim,
+            target_vec.len()
+        )));
+    }
+
+    let target_mag = magnitude(&target_vec);
+    let n = candidates_flat.len() / row_dim;
+
+    if target_mag == 0.0 {
+        return Ok(vec![0.0; n]);
+    }
+
+    let results = candidates_flat
+        .chunks_exact(row_dim)
+        .map(|candidate| cosine_with_magnitude(&target_vec, target_mag, candidate))
+        .collect();
+
+    Ok(results)
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ZeroVectorPolicy {
+    // Zero vectors have similarity 0.0 (the behaviour of the plain functions)
+    #[default]
+    Zero,
+    // Zero vectors have similarity NaN so they can be filtered downstream
+    Nan,
+    // Zero vectors are rejected with an error
+    Error,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct SimilarityOptions {
+    #[serde(rename = "zeroVectorPolicy", default)]
+    pub zero_vector_policy: ZeroVectorPolicy,
+}
+
+fn parse_similarity_options(options_js: JsValue) -> Result<SimilarityOptions, JsValue> {
+    if options_js.is_null() || options_js.is_undefined() {
+        return Ok(SimilarityOptions::default());
+    }
+    serde_wasm_bindgen::from_value(options_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize similarity options: {}", e)))
+}
+
+/// `cosine_similarity_one_to_many` with control over zero vectors via
+/// `{ zeroVectorPolicy: "zero" | "nan" | "error" }`. Defaults to "zero".
+#[wasm_bindgen]
+pub fn cosine_similarity_one_to_many_opts(
+    target_vec_js: JsValue,
+    candidate_vecs_js: JsValue,
+    options_js: JsValue,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let target_vec: Vec<f64> = serde_wasm_bindgen::from_value(target_vec_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize target_vec: {}", e)))?;
+    let candidates: Vec<Vec<f64>> = serde_wasm_bindgen::from_value(candidate_vecs_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize candidate_vecs: {}", e)))?;
+    let policy = parse_similarity_options(options_js)?.zero_vector_policy;
+
+    if target_vec.is_empty() {
+        return Err(JsValue::from_str("Target vector is empty."));
+    }
+
+    let target_mag = magnitude(&target_vec);
+
+    if target_mag == 0.0 && policy == ZeroVectorPolicy::Error {
+        return Err(JsValue::from_str("Target vector is a zero vector."));
+    }
+
+    let mut results = Vec::with_capacity(candidates.len());
+
+    for (i, candidate) in candidates.iter().enumerate() {
+        if candidate.len() != target_vec.len() {
+            results.push(0.0); // Dimension mismatch
+            continue;
+        }
+
+        // A zero target under "nan" still keeps the mismatch sentinel above
+        if policy == ZeroVectorPolicy::Nan && target_mag == 0.0 {
+            results.push(f64::NAN);
+            continue;
+        }
+
+        if policy != ZeroVectorPolicy::Zero && candidate.iter().all(|&v| v == 0.0) {
+            if policy == ZeroVectorPolicy::Error {
+                return Err(JsValue::from_str(&format!("Candidate {} is a zero vector.", i)));
+            }
+            results.push(f64::NAN);
+            continue;
+        }
+
+        results.push(cosine_with_magnitude(&target_vec, target_mag, candidate));
+    }
+
+    Ok(serde_wasm_bindgen::to_value(&results)?)
+}
+
+// L-infinity distance: the largest absolute coordinate difference.
+fn chebyshev(vec1: &[f64], vec2: &[f64]) -> f64 {
+    vec1.iter()
+        .zip(vec2.iter())
+        .map(|(&a, &b)| (a - b).abs())
+        .fold(0.0, f64::max)
+}
+
+#[wasm_bindgen]
+pub fn chebyshev_distance(
+    vec1_js: JsValue,
+    vec2_js: JsValue,
+) -> Result<f64, JsValue> {
+    utils::set_panic_hook();
+
+    let vec1: Vec<f64> = serde_wasm_bindgen::from_value(vec1_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vec1: {}", e)))?;
+    let vec2: Vec<f64> = serde_wasm_bindgen::from_value(vec2_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vec2: {}", e)))?;
+
+    if vec1.len() != vec2.len() {
+        return Err(JsValue::from_str("Vector dimensions mismatch."));
+    }
+    if vec1.is_empty() {
+        return Err(JsValue::from_str("Vectors cannot be empty."));
+    }
+
+    Ok(chebyshev(&vec1, &vec2))
+}
+
+#[wasm_bindgen]
+pub fn chebyshev_distance_one_to_many(
+    target_vec_js: JsValue,
+    candidate_vecs_js: JsValue,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let target_vec: Vec<f64> = serde_wasm_bindgen::from_value(target_vec_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize target_vec: {}", e)))?;
+    let candidates: Vec<Vec<f64>> = serde_wasm_bindgen::from_value(candidate_vecs_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize candidate_vecs: {}", e)))?;
+
+    if target_vec.is_empty() {
+        return Err(JsValue::from_str("Target vector is empty."));
+    }
+
+    let mut results = Vec::with_capacity(candidates.len());
+
+    for candidate in candidates {
+        if candidate.len() != target_vec.len() {
+            results.push(f64::INFINITY); // Dimension mismatch
+            continue;
+        }
+        results.push(chebyshev(&target_vec, &candidate));
+    }
+
+    Ok(serde_wasm_bindgen::to_value(&results)?)
+}
+
+/// Cosine similarity of every target against every candidate, as a
+/// `targets x candidates` matrix. Candidate magnitudes are computed once and
+/// shared by all targets. All vectors must have the same dimension; zero
+/// vectors score 0.
+#[wasm_bindgen]
+pub fn cosine_similarity_many_to_many(
+    targets_js: JsValue,
+    candidates_js: JsValue,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let targets: Vec<Vec<f64>> = serde_wasm_bindgen::from_value(targets_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize targets: {}", e)))?;
+    let candidates: Vec<Vec<f64>> = serde_wasm_bindgen::from_value(candidates_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize candidates: {}", e)))?;
+
+    let dim = match targets.first().or(candidates.first()) {
+        Some(v) => v.len(),
+        None => return Ok(serde_wasm_bindgen::to_value(&Vec::<Vec<f64>>::new())?),
+    };
+    for (i, t) in targets.iter().enumerate() {
+        if t.len() != dim {
+            return Err(JsValue::from_str(&format!(
+                "Target {} dimension mismatch: expected {}, got {}",
+                i,
+                dim,
+                t.len()
+            )));
+        }
+    }
+    for (i, c) in candidates.iter().enumerate() {
+        if c.len() != dim {
+            return Err(JsValue::from_str(&format!(
+                "Candidate {} dimension mismatch: expected {}, got {}",
+                i,
+                dim,
+                c.len()
+            )));
+        }
+    }
+
+    let candidate_mags: Vec<f64> = candidates.iter().map(|c| magnitude(c)).collect();
+
+    let mut results = Vec::with_capacity(targets.len());
+
+    for target in &targets {
+        let target_mag = magnitude(target);
+        let row: Vec<f64> = candidates
+            .iter()
+            .zip(candidate_mags.iter())
+            .map(|(candidate, &cand_mag)| cosine_from_parts(simd::dot(target, candidate), target_mag, cand_mag))
+            .collect();
+        results.push(row);
+    }
+
+    Ok(serde_wasm_bindgen::to_value(&results)?)
+}
+
+/// Top `k` candidates by cosine similarity as `{ index, score }`, highest
+/// first, with ties going to the lower index. Uses a bounded heap in a single
+/// pass, so neither a full result array nor a full sort is built. Candidates
+/// whose dimension differs from the target are skipped.
+#[wasm_bindgen]
+pub fn cosine_top_k(
+    target_vec_js: JsValue,
+    candidate_vecs_js: JsValue,
+    k: usize,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let target_vec: Vec<f64> = serde_wasm_bindgen::from_value(target_vec_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize target_vec: {}", e)))?;
+    let candidates: Vec<Vec<f64>> = serde_wasm_bindgen::from_value(candidate_vecs_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize candidate_vecs: {}", e)))?;
+
+    if target_vec.is_empty() {
+        return Err(JsValue::from_str("Target vector is empty."));
+    }
+
+    let target_mag = magnitude(&target_vec);
+    let entries = candidates
+        .iter()
+        .enumerate()
+        .filter(|(_, candidate)| candidate.len() == target_vec.len())
+        .map(|(index, candidate)| HeapEntry {
+            score: cosine_with_magnitude(&target_vec, target_mag, candidate),
+            key: index,
+            index,
+        });
+
+    let results: Vec<IndexedScore> = top_k_entries(entries, k.min(candidates.len()))
+        .into_iter()
+        .map(|e| IndexedScore { index: e.index, score: e.score })
+        .collect();
+
+    Ok(serde_wasm_bindgen::to_value(&results)?)
+}
+
+/// Mahalanobis-style distance `sqrt((a - b)^T A_inv (a - b))` under the
+/// model's own A_inv. As in the UCB exploration term, abs() is taken before
+/// the square root so tiny negative values from rounding don't yield NaN.
+#[wasm_bindgen]
+pub fn mahalanobis_distance(
+    model_js: JsValue,
+    vec1_js: JsValue,
+    vec2_js: JsValue,
+) -> Result<f64, JsValue> {
+    utils::set_panic_hook();
+
+    let model: BanditModel = serde_wasm_bindgen::from_value(model_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize BanditModel: {}", e)))?;
+    let vec1: Vec<f64> = serde_wasm_bindgen::from_value(vec1_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vec1: {}", e)))?;
+    let vec2: Vec<f64> = serde_wasm_bindgen::from_value(vec2_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vec2: {}", e)))?;
+
+    let d = model.dimension;
+
+    validate_bandit_model(&model).map_err(|e| JsValue::from_str(&e))?;
+    validate_embedding(&vec1, d).map_err(|e| JsValue::from_str(&format!("vec1: {}", e)))?;
+    validate_embedding(&vec2, d).map_err(|e| JsValue::from_str(&format!("vec2: {}", e)))?;
+
+    let a_inv = ArrayView2::from_shape((d, d), &model.a_inv)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let diff = &ArrayView::from(&vec1) - &ArrayView::from(&vec2);
+
+    let distance = diff.dot(&a_inv.dot(&diff)).abs().sqrt();
+    if !distance.is_finite() {
+        return Err(JsValue::from_str("Mahalanobis distance is non-finite (NaN/Inf)."));
+    }
+
+    Ok(distance)
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ClusteringResult {
+    pub assignments: Vec<usize>,
+    pub centroids: Vec<Vec<f64>>,
+}
+
+/// Spherical k-means: vectors are L2-normalized, assigned to the centroid with
+/// the highest cosine similarity, and each centroid is the normalized sum of
+/// its members. Initial centroids are `k` distinct vectors chosen with `seed`,
+/// so runs are reproducible. Stops after `max_iters` rounds or once no
+/// assignment changes; a cluster that empties keeps its previous centroid.
+#[wasm_bindgen]
+pub fn spherical_kmeans(
+    vectors_js: JsValue,
+    k: usize,
+    max_iters: usize,
+    seed: u64,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let mut vectors: Vec<Vec<f64>> = serde_wasm_bindgen::from_value(vectors_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vectors: {}", e)))?;
+
+    let n = vectors.len();
+    if k == 0 {
+        return Err(JsValue::from_str("k must be greater than 0."));
+    }
+    if k > n {
+        return Err(JsValue::from_str(&format!("k ({}) cannot exceed the number of vectors ({}).", k, n)));
+    }
+    let dim = vectors[0].len();
+    if dim == 0 {
+        return Err(JsValue::from_str("Vectors cannot be empty."));
+    }
+    if let Some(i) = vectors.iter().position(|v| v.len() != dim) {
+        return Err(JsValue::from_str(&format!(
+            "Vector {} dimension mismatch: expected {}, got {}",
+            i,
+            dim,
+            vectors[i].len()
+        )));
+    }
+
+    for vec in vectors.iter_mut() {
+        l2_normalize(vec);
+    }
+
+    // Partial Fisher-Yates: the first k entries become distinct seed indices
+    let mut rng = rng::SplitMix64::new(seed);
+    let mut order: Vec<usize> = (0..n).collect();
+    for i in 0..k {
+        let j = i + (rng.next_u64() % (n - i) as u64) as usize;
+        order.swap(i, j);
+    }
+    let mut centroids: Vec<Vec<f64>> = order[..k].iter().map(|&i| vectors[i].clone()).collect();
+
+    let assign = |centroids: &[Vec<f64>], vec: &[f64]| -> usize {
+        let mut best = 0;
+        let mut best_score = f64::NEG_INFINITY;
+        for (c, centroid) in centroids.iter().enumerate() {
+            let score = simd::dot(vec, centroid);
+            if score > best_score {
+                best = c;
+                best_score = score;
+            }
+        }
+        best
+    };
+
+    let mut assignments: Vec<usize> = vectors.iter().map(|v| assign(&centroids, v)).collect();
+
+    for _ in 0..max_iters {
+        let mut sums = vec![vec![0.0; dim]; k];
+        let mut counts = vec![0usize; k];
+        for (vec, &c) in vectors.iter().zip(assignments.iter()) {
+            for (s, &v) in sums[c].iter_mut().zip(vec.iter()) {
+                *s += v;
+            }
+            counts[c] += 1;
+        }
+        for (c, mut sum) in sums.into_iter().enumerate() {
+            if counts[c] > 0 {
+                l2_normalize(&mut sum);
+                centroids[c] = sum;
+            }
+        }
+
+        let next: Vec<usize> = vectors.iter().map(|v| assign(&centroids, v)).collect();
+        if next == assignments {
+            break;
+        }
+        assignments = next;
+    }
+
+    let result = ClusteringResult { assignments, centroids };
+
+    Ok(serde_wasm_bindgen::to_value(&result)?)
+}
+
+// Welford running mean and sum of squared deviations (M2) per dimension.
+#[derive(Serialize, Deserialize)]
+pub struct RunningStats {
+    pub count: u64,
+    pub mean: Vec<f64>,
+    pub m2: Vec<f64>,
+}
+
+/// Folds one embedding into `stats` with Welford's update and returns the new
+/// stats. Pass `null`/`undefined` to start from an empty state sized to
+/// `embedding`.
+#[wasm_bindgen]
+pub fn update_stats(stats_js: JsValue, embedding: &[f64]) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let mut stats = if stats_js.is_null() || stats_js.is_undefined() {
+        RunningStats {
+            count: 0,
+            mean: vec![0.0; embedding.len()],
+            m2: vec![0.0; embedding.len()],
+        }
+    } else {
+        serde_wasm_bindgen::from_value(stats_js)
+            .map_err(|e| JsValue::from_str(&format!("Failed to deserialize RunningStats: {}", e)))?
+    };
+
+    if stats.mean.len() != stats.m2.len() {
+        return Err(JsValue::from_str("RunningStats mean and m2 length mismatch."));
+    }
+    validate_embedding(embedding, stats.mean.len()).map_err(|e| JsValue::from_str(&e))?;
+
+    stats.count += 1;
+    let n = stats.count as f64;
+    for ((mean, m2), &x) in stats.mean.iter_mut().zip(stats.m2.iter_mut()).zip(embedding.iter()) {
+        let delta = x - *mean;
+        *mean += delta / n;
+        *m2 += delta * (x - *mean);
+    }
+
+    Ok(serde_wasm_bindgen::to_value(&stats)?)
+}
+
+/// Standardizes `embedding` as `(x - mean) / std` per dimension, using the
+/// population standard deviation `sqrt(M2 / count)`. Dimensions with zero std
+/// (including every dimension before any update) pass `x` through unchanged.
+#[wasm_bindgen]
+pub fn standardize(stats_js: JsValue, embedding: &[f64]) -> Result<Vec<f64>, JsValue> {
+    utils::set_panic_hook();
+
+    let stats: RunningStats = serde_wasm_bindgen::from_value(stats_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize RunningStats: {}", e)))?;
+
+    if stats.mean.len() != stats.m2.len() {
+        return Err(JsValue::from_str("RunningStats mean and m2 length mismatch."));
+    }
+    validate_embedding(embedding, stats.mean.len()).map_err(|e| JsValue::from_str(&e))?;
+
+    let n = stats.count as f64;
+    let standardized = embedding
+        .iter()
+        .zip(stats.mean.iter().zip(stats.m2.iter()))
+        .map(|(&x, (&mean, &m2))| {
+            let std = if stats.count == 0 { 0.0 } else { (m2 / n).sqrt() };
+            if std > 0.0 { (x - mean) / std } else { x }
+        })
+        .collect();
+
+    Ok(standardized)
+}
+
+/// Soft cosine `v1^T S v2 / (sqrt(v1^T S v1) * sqrt(v2^T S v2))`, where `S` is
+/// a row-major `dim x dim` feature-similarity matrix relating dimensions to
+/// each other. With `S = I` this is plain cosine similarity. Returns 0.0 when
+/// either self-similarity is not positive (zero vectors, or an `S` that is
+/// not positive semi-definite).
+#[wasm_bindgen]
+pub fn soft_cosine_similarity(
+    vec1_js: JsValue,
+    vec2_js: JsValue,
+    feature_sim_flat_js: JsValue,
+    dim: usize,
+) -> Result<f64, JsValue> {
+    utils::set_panic_hook();
+
+    let vec1: Vec<f64> = serde_wasm_bindgen::from_value(vec1_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vec1: {}", e)))?;
+    let vec2: Vec<f64> = serde_wasm_bindgen::from_value(vec2_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vec2: {}", e)))?;
+    let feature_sim: Vec<f64> = serde_wasm_bindgen::from_value(feature_sim_flat_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize feature_sim_flat: {}", e)))?;
+
+    if dim == 0 {
+        return Err(JsValue::from_str("dim must be greater than 0."));
+    }
+    if vec1.len() != dim || vec2.len() != dim {
+        return Err(JsValue::from_str("Vector dimensions mismatch."));
+    }
+    let s = ArrayView2::from_shape((dim, dim), &feature_sim).map_err(|_| {
+        JsValue::from_str(&format!(
+            "Feature similarity matrix length mismatch: expected {}, got {}",
+            dim * dim,
+            feature_sim.len()
+        ))
+    })?;
+
+    let v1 = ArrayView::from(&vec1);
+    let v2 = ArrayView::from(&vec2);
+    let s_v1 = s.dot(&v1);
+    let s_v2 = s.dot(&v2);
+
+    let self1 = v1.dot(&s_v1);
+    let self2 = v2.dot(&s_v2);
+    if self1 <= 0.0 || self2 <= 0.0 {
+        return Ok(0.0);
+    }
+
+    Ok(v1.dot(&s_v2) / (self1.sqrt() * self2.sqrt()))
+}
+
+// Metrics selectable by name in `similarity_one_to_many`; `distance_matrix` reuses a subset.
+#[derive(Clone, Copy)]
+enum Metric {
+    Cosine,
+    Dot,
+    Euclidean,
+    Manhattan,
+}
+
+impl Metric {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "cosine" => Ok(Metric::Cosine),
+            "dot" => Ok(Metric::Dot),
+            "euclidean" => Ok(Metric::Euclidean),
+            "manhattan" => Ok(Metric::Manhattan),
+            _ => Err(format!("Unknown metric: {}", name)),
+        }
+    }
+
+    // Callers guarantee equal, non-zero lengths.
+    fn compute(self, vec1: &[f64], vec2: &[f64]) -> f64 {
+        match self {
+            Metric::Cosine => cosine(vec1, vec2),
+            Metric::Dot => simd::dot(vec1, vec2),
+            Metric::Euclidean => squared_distance(vec1, vec2).sqrt(),
+            Metric::Manhattan => manhattan(vec1, vec2),
+        }
+    }
+
+    // Same mismatch value as the metric's own one-to-many function.
+    fn mismatch_sentinel(self) -> f64 {
+        match self {
+            Metric::Euclidean | Metric::Manhattan => f64::INFINITY,
+            Metric::Cosine | Metric::Dot => 0.0,
+        }
+    }
+}
+
+/// One entry point for the cosine, dot, euclidean and manhattan one-to-many
+/// metrics, selected by name. Results and dimension-mismatch sentinels match
+/// the per-metric functions.
+#[wasm_bindgen]
+pub fn similarity_one_to_many(
+    target_vec_js: JsValue,
+    candidate_vecs_js: JsValue,
+    metric: &str,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let metric = Metric::parse(metric).map_err(|e| JsValue::from_str(&e))?;
+
+    let target_vec: Vec<f64> = serde_wasm_bindgen::from_value(target_vec_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize target_vec: {}", e)))?;
+    let candidates: Vec<Vec<f64>> = serde_wasm_bindgen::from_value(candidate_vecs_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize candidate_vecs: {}", e)))?;
+
+    if target_vec.is_empty() {
+        return Err(JsValue::from_str("Target vector is empty."));
+    }
+
+    let results: Vec<f64> = candidates
+        .iter()
+        .map(|candidate| {
+            if candidate.len() != target_vec.len() {
+                metric.mismatch_sentinel() // Dimension mismatch
+            } else {
+                metric.compute(&target_vec, candidate)
+            }
+        })
+        .collect();
+
+    Ok(serde_wasm_bindgen::to_value(&results)?)
+}
 
-    for i in 0..n {
-        for j in i..n { // 対称行列なので、半分だけ計算してコピー
-            let vec1 = &vectors[i];
-            let vec2 = &vectors[j];
+// 1-based ranks by ascending value, with tied values sharing their average rank.
+fn average_ranks(values: &[f64]) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&i, &j| values[i].total_cmp(&values[j]));
 
-            if vec1.len() != vec2.len() || vec1.is_empty() {
-                similarity_matrix[i][j] = 0.0;
-                similarity_matrix[j][i] = 0.0;
-                continue;
-            }
+    let mut ranks = vec![0.0; values.len()];
+    let mut start = 0;
+    while start < order.len() {
+        let mut end = start + 1;
+        while end < order.len() && values[order[end]] == values[order[start]] {
+            end += 1;
+        }
+        // positions start..end hold ranks start+1..=end
+        let rank = (start + 1 + end) as f64 / 2.0;
+        for &i in &order[start..end] {
+            ranks[i] = rank;
+        }
+        start = end;
+    }
+    ranks
+}
 
-            let dot_product: f64 = vec1.iter().zip(vec2.iter()).map(|(&a, &b)| a * b).sum();
-            let magnitude1: f64 = vec1.iter().map(|&a| a * a).sum::<f64>().sqrt();
-            let magnitude2: f64 = vec2.iter().map(|&b| b * b).sum::<f64>().sqrt();
+/// Spearman rank correlation: Pearson correlation of the average ranks of
+/// two score vectors, for comparing how two models order the same items.
+/// A constant input has no rank variation and gives 0.
+#[wasm_bindgen]
+pub fn spearman_correlation(
+    scores_a_js: JsValue,
+    scores_b_js: JsValue,
+) -> Result<f64, JsValue> {
+    utils::set_panic_hook();
 
-            if magnitude1 == 0.0 || magnitude2 == 0.0 {
-                similarity_matrix[i][j] = 0.0;
-                similarity_matrix[j][i] = 0.0;
-            } else {
-                let similarity = dot_product / (magnitude1 * magnitude2);
-                similarity_matrix[i][j] = similarity;
-                similarity_matrix[j][i] = similarity; // 対称性を利用
-            }
+    let scores_a: Vec<f64> = serde_wasm_bindgen::from_value(scores_a_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize scores_a: {}", e)))?;
+    let scores_b: Vec<f64> = serde_wasm_bindgen::from_value(scores_b_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize scores_b: {}", e)))?;
+
+    if scores_a.len() != scores_b.len() {
+        return Err(JsValue::from_str("Score vectors length mismatch."));
+    }
+    if scores_a.len() < 2 {
+        return Err(JsValue::from_str("At least two scores are required."));
+    }
+    if scores_a.iter().chain(scores_b.iter()).any(|v| !v.is_finite()) {
+        return Err(JsValue::from_str("Scores contain non-finite values (NaN/Inf)."));
+    }
+
+    Ok(pearson(&average_ranks(&scores_a), &average_ranks(&scores_b)))
+}
+
+/// `cosine_similarity_one_to_many` over int8-quantized embeddings, each with
+/// its own scale. Values are dequantized as `value * scale` inside the dot
+/// product and magnitude loops, so no f64 copy of the candidates is built.
+#[wasm_bindgen]
+pub fn cosine_similarity_one_to_many_i8(
+    target_js: JsValue,
+    target_scale: f64,
+    candidates_js: JsValue,
+    scales_js: JsValue,
+) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let target: Vec<i8> = serde_wasm_bindgen::from_value(target_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize target: {}", e)))?;
+    let candidates: Vec<Vec<i8>> = serde_wasm_bindgen::from_value(candidates_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize candidates: {}", e)))?;
+    let scales: Vec<f64> = serde_wasm_bindgen::from_value(scales_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize scales: {}", e)))?;
+
+    if target.is_empty() {
+        return Err(JsValue::from_str("Target vector is empty."));
+    }
+    if scales.len() != candidates.len() {
+        return Err(JsValue::from_str(&format!(
+            "Scales length mismatch: expected {}, got {}",
+            candidates.len(),
+            scales.len()
+        )));
+    }
+    if !target_scale.is_finite() || target_scale == 0.0 {
+        return Err(JsValue::from_str("target_scale must be finite and non-zero."));
+    }
+    if let Some(i) = scales.iter().position(|s| !s.is_finite() || *s == 0.0) {
+        return Err(JsValue::from_str(&format!(
+            "Scale at index {} must be finite and non-zero.",
+            i
+        )));
+    }
+
+    let target_mag = target
+        .iter()
+        .map(|&a| {
+            let v = a as f64 * target_scale;
+            v * v
+        })
+        .sum::<f64>()
+        .sqrt();
+
+    let mut results = Vec::with_capacity(candidates.len());
+
+    for (candidate, &scale) in candidates.iter().zip(scales.iter()) {
+        if candidate.len() != target.len() || target_mag == 0.0 {
+            results.push(0.0); // Dimension mismatch or zero target
+            continue;
+        }
+
+        let mut dot_product = 0.0;
+        let mut cand_mag_sq = 0.0;
+        for (&a, &b) in target.iter().zip(candidate.iter()) {
+            let y = b as f64 * scale;
+            dot_product += a as f64 * target_scale * y;
+            cand_mag_sq += y * y;
         }
+
+        results.push(cosine_from_parts(dot_product, target_mag, cand_mag_sq.sqrt()));
     }
 
+    Ok(serde_wasm_bindgen::to_value(&results)?)
+}
+
+// Squared Euclidean distance ||a - b||^2.
+fn squared_distance(vec1: &[f64], vec2: &[f64]) -> f64 {
+    vec1.iter().zip(vec2.iter()).map(|(&a, &b)| (a - b) * (a - b)).sum()
+}
 
-    Ok(serde_wasm_bindgen::to_value(&similarity_matrix)?)
+fn validate_rbf_gamma(gamma: f64) -> Result<(), JsValue> {
+    if !gamma.is_finite() || gamma <= 0.0 {
+        return Err(JsValue::from_str("gamma must be a positive finite number."));
+    }
+    Ok(())
 }
 
+/// Gaussian (RBF) kernel similarity `exp(-gamma * ||a - b||^2)`, in (0, 1]
+/// with 1 for identical vectors.
 #[wasm_bindgen]
-pub fn cosine_similarity_one_to_many(
+pub fn rbf_similarity(
+    vec1_js: JsValue,
+    vec2_js: JsValue,
+    gamma: f64,
+) -> Result<f64, JsValue> {
+    utils::set_panic_hook();
+
+    let vec1: Vec<f64> = serde_wasm_bindgen::from_value(vec1_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vec1: {}", e)))?;
+    let vec2: Vec<f64> = serde_wasm_bindgen::from_value(vec2_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vec2: {}", e)))?;
+
+    validate_rbf_gamma(gamma)?;
+    if vec1.len() != vec2.len() {
+        return Err(JsValue::from_str("Vector dimensions mismatch."));
+    }
+    if vec1.is_empty() {
+        return Err(JsValue::from_str("Vectors cannot be empty."));
+    }
+
+    Ok((-gamma * squared_distance(&vec1, &vec2)).exp())
+}
+
+#[wasm_bindgen]
+pub fn rbf_similarity_one_to_many(
     target_vec_js: JsValue,
     candidate_vecs_js: JsValue,
+    gamma: f64,
 ) -> Result<JsValue, JsValue> {
     utils::set_panic_hook();
 
@@ -320,44 +5308,561 @@ pub fn cosine_similarity_one_to_many(
     let candidates: Vec<Vec<f64>> = serde_wasm_bindgen::from_value(candidate_vecs_js)
         .map_err(|e| JsValue::from_str(&format!("Failed to deserialize candidate_vecs: {}", e)))?;
 
+    validate_rbf_gamma(gamma)?;
     if target_vec.is_empty() {
-         return Err(JsValue::from_str("Target vector is empty."));
+        return Err(JsValue::from_str("Target vector is empty."));
     }
 
     let mut results = Vec::with_capacity(candidates.len());
-    
-    // Pre-calculate target vector magnitude
-    let target_mag_sq: f64 = target_vec.iter().map(|&a| a * a).sum();
-    let target_mag = target_mag_sq.sqrt();
-
-    if target_mag == 0.0 {
-         // If target vector is zero, all similarities are 0
-         results.resize(candidates.len(), 0.0);
-         return Ok(serde_wasm_bindgen::to_value(&results)?);
-    }
 
     for candidate in candidates {
         if candidate.len() != target_vec.len() {
-             results.push(0.0); // Dimension mismatch
-             continue;
+            results.push(0.0); // Dimension mismatch
+            continue;
         }
+        results.push((-gamma * squared_distance(&target_vec, &candidate)).exp());
+    }
 
-        let mut dot_product = 0.0;
-        let mut cand_mag_sq = 0.0;
+    Ok(serde_wasm_bindgen::to_value(&results)?)
+}
 
-        for (a, b) in target_vec.iter().zip(candidate.iter()) {
-            dot_product += a * b;
-            cand_mag_sq += b * b;
+// P² (Jain & Chlamtac 1985) streaming estimator for quantile `p`. Until five
+// scores have been seen, `heights` holds them sorted; afterwards it holds the
+// five marker heights, with `positions` the actual and `desired` the ideal
+// marker positions.
+#[derive(Serialize, Deserialize)]
+pub struct PercentileEstimator {
+    pub p: f64,
+    pub count: u64,
+    pub heights: Vec<f64>,
+    pub positions: Vec<f64>,
+    pub desired: Vec<f64>,
+}
+
+impl PercentileEstimator {
+    // Quantiles tracked by the five markers; also the per-score increments of
+    // their desired positions.
+    fn marker_quantiles(&self) -> [f64; 5] {
+        [0.0, self.p / 2.0, self.p, (1.0 + self.p) / 2.0, 1.0]
+    }
+
+    fn update(&mut self, x: f64) {
+        self.count += 1;
+
+        if self.positions.is_empty() {
+            let at = self.heights.partition_point(|&h| h <= x);
+            self.heights.insert(at, x);
+            if self.heights.len() == 5 {
+                let p = self.p;
+                self.positions = vec![1.0, 2.0, 3.0, 4.0, 5.0];
+                self.desired = vec![1.0, 1.0 + 2.0 * p, 1.0 + 4.0 * p, 3.0 + 2.0 * p, 5.0];
+            }
+            return;
         }
 
-        let cand_mag = cand_mag_sq.sqrt();
-        
-        if cand_mag == 0.0 {
-            results.push(0.0);
+        let increments = self.marker_quantiles();
+        let q = &mut self.heights;
+        let n = &mut self.positions;
+
+        // Cell k with q[k] <= x < q[k + 1], extending the extremes if needed
+        let k = if x < q[0] {
+            q[0] = x;
+            0
+        } else if x >= q[4] {
+            q[4] = x;
+            3
         } else {
-            results.push(dot_product / (target_mag * cand_mag));
+            (0..4).find(|&i| x < q[i + 1]).unwrap_or(3)
+        };
+
+        for pos in n.iter_mut().skip(k + 1) {
+            *pos += 1.0;
+        }
+        for (want, dn) in self.desired.iter_mut().zip(increments) {
+            *want += dn;
+        }
+
+        for i in 1..4 {
+            let d = self.desired[i] - n[i];
+            if (d >= 1.0 && n[i + 1] - n[i] > 1.0) || (d <= -1.0 && n[i - 1] - n[i] < -1.0) {
+                let d = d.signum();
+                let parabolic = q[i]
+                    + d / (n[i + 1] - n[i - 1])
+                        * ((n[i] - n[i - 1] + d) * (q[i + 1] - q[i]) / (n[i + 1] - n[i])
+                            + (n[i + 1] - n[i] - d) * (q[i] - q[i - 1]) / (n[i] - n[i - 1]));
+                q[i] = if q[i - 1] < parabolic && parabolic < q[i + 1] {
+                    parabolic
+                } else {
+                    let j = if d > 0.0 { i + 1 } else { i - 1 };
+                    q[i] + d * (q[j] - q[i]) / (n[j] - n[i])
+                };
+                n[i] += d;
+            }
         }
     }
 
-    Ok(serde_wasm_bindgen::to_value(&results)?)
+    // Exact at the tracked `p`; other quantiles interpolate linearly between
+    // neighbouring markers.
+    fn estimate(&self, p: f64) -> f64 {
+        let ys = &self.heights;
+        let xs: Vec<f64> = if self.positions.is_empty() {
+            let last = (ys.len() - 1).max(1) as f64;
+            (0..ys.len()).map(|i| i as f64 / last).collect()
+        } else {
+            self.marker_quantiles().to_vec()
+        };
+
+        if ys.len() == 1 {
+            return ys[0];
+        }
+        let i = xs.partition_point(|&x| x < p).clamp(1, xs.len() - 1);
+        let (x0, x1) = (xs[i - 1], xs[i]);
+        if x1 == x0 {
+            return ys[i];
+        }
+        ys[i - 1] + (ys[i] - ys[i - 1]) * (p - x0) / (x1 - x0)
+    }
+}
+
+/// Creates an empty P² estimator that tracks quantile `p` of a score stream
+/// in constant memory.
+#[wasm_bindgen]
+pub fn create_percentile_estimator(p: f64) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    if !(p > 0.0 && p < 1.0) {
+        return Err(JsValue::from_str("p must be strictly between 0 and 1."));
+    }
+
+    let estimator = PercentileEstimator {
+        p,
+        count: 0,
+        heights: Vec::with_capacity(5),
+        positions: Vec::new(),
+        desired: Vec::new(),
+    };
+
+    Ok(serde_wasm_bindgen::to_value(&estimator)?)
+}
+
+/// Feeds one score into the estimator and returns the new state.
+#[wasm_bindgen]
+pub fn update_percentile(est_js: JsValue, score: f64) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let mut estimator: PercentileEstimator = serde_wasm_bindgen::from_value(est_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize PercentileEstimator: {}", e)))?;
+
+    if !score.is_finite() {
+        return Err(JsValue::from_str("Score is non-finite (NaN/Inf)."));
+    }
+    if !(estimator.p > 0.0 && estimator.p < 1.0) {
+        return Err(JsValue::from_str("p must be strictly between 0 and 1."));
+    }
+    if (estimator.positions.is_empty() && estimator.heights.len() >= 5)
+        || (!estimator.positions.is_empty()
+            && (estimator.heights.len() != 5 || estimator.positions.len() != 5 || estimator.desired.len() != 5))
+    {
+        return Err(JsValue::from_str("PercentileEstimator state is corrupted."));
+    }
+
+    estimator.update(score);
+
+    Ok(serde_wasm_bindgen::to_value(&estimator)?)
+}
+
+/// Estimated `p`-quantile of the scores seen so far. Most accurate at the
+/// estimator's own `p`; during the first five scores it is the exact
+/// interpolated sample quantile.
+#[wasm_bindgen]
+pub fn get_percentile(est_js: JsValue, p: f64) -> Result<f64, JsValue> {
+    utils::set_panic_hook();
+
+    let estimator: PercentileEstimator = serde_wasm_bindgen::from_value(est_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize PercentileEstimator: {}", e)))?;
+
+    if !(0.0..=1.0).contains(&p) {
+        return Err(JsValue::from_str("p must be between 0 and 1."));
+    }
+    if estimator.heights.is_empty() {
+        return Err(JsValue::from_str("PercentileEstimator has no observations."));
+    }
+    if !estimator.positions.is_empty() && estimator.heights.len() != 5 {
+        return Err(JsValue::from_str("PercentileEstimator state is corrupted."));
+    }
+
+    Ok(estimator.estimate(p))
+}
+
+/// Element-wise mean of `vectors`, e.g. a user-interest centroid from recently
+/// liked articles. With `normalize`, the result is L2-normalized (a zero mean
+/// stays zero). All vectors must share one non-zero dimension.
+#[wasm_bindgen]
+pub fn centroid(vectors_js: JsValue, normalize: Option<bool>) -> Result<Vec<f64>, JsValue> {
+    utils::set_panic_hook();
+
+    let vectors: Vec<Vec<f64>> = serde_wasm_bindgen::from_value(vectors_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vectors: {}", e)))?;
+
+    let dim = match vectors.first() {
+        Some(v) if !v.is_empty() => v.len(),
+        Some(_) => return Err(JsValue::from_str("Vectors cannot be empty.")),
+        None => return Err(JsValue::from_str("At least one vector is required.")),
+    };
+    if let Some(i) = vectors.iter().position(|v| v.len() != dim) {
+        return Err(JsValue::from_str(&format!(
+            "Vector {} dimension mismatch: expected {}, got {}",
+            i,
+            dim,
+            vectors[i].len()
+        )));
+    }
+
+    let mut mean = vec![0.0; dim];
+    for vec in &vectors {
+        for (m, &v) in mean.iter_mut().zip(vec.iter()) {
+            *m += v;
+        }
+    }
+    let n = vectors.len() as f64;
+    for m in mean.iter_mut() {
+        *m /= n;
+    }
+
+    if normalize.unwrap_or(false) {
+        l2_normalize(&mut mean);
+    }
+
+    Ok(mean)
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SimilarPair {
+    pub i: usize,
+    pub j: usize,
+    pub similarity: f64,
+}
+
+/// Pairs `i < j` whose cosine similarity is at least `threshold`, found with
+/// the same upper-triangle traversal as `calculate_similarity_matrix` but
+/// without materializing the matrix. Pairs with different dimensions are
+/// never reported.
+#[wasm_bindgen]
+pub fn find_duplicates(vectors_js: JsValue, threshold: f64) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let vectors: Vec<Vec<f64>> = serde_wasm_bindgen::from_value(vectors_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vectors: {}", e)))?;
+
+    if !(-1.0..=1.0).contains(&threshold) {
+        return Err(JsValue::from_str("threshold must be between -1 and 1."));
+    }
+
+    let magnitudes: Vec<f64> = vectors.iter().map(|v| magnitude(v)).collect();
+    let n = vectors.len();
+    let mut pairs: Vec<SimilarPair> = Vec::new();
+
+    for i in 0..n {
+        for j in (i + 1)..n {
+            if vectors[i].len() != vectors[j].len() {
+                continue;
+            }
+            let similarity = cosine_with_magnitude(&vectors[i], magnitudes[i], &vectors[j]);
+            if similarity >= threshold {
+                pairs.push(SimilarPair { i, j, similarity });
+            }
+        }
+    }
+
+    Ok(serde_wasm_bindgen::to_value(&pairs)?)
+}
+
+/// The `k` most similar pairs `i < j` by cosine similarity, highest first,
+/// with ties going to the lexicographically smaller `(i, j)`. Streams the
+/// upper triangle through a bounded heap instead of sorting all n^2 / 2
+/// pairs. Pairs with different dimensions are never reported.
+#[wasm_bindgen]
+pub fn top_k_similar_pairs(vectors_js: JsValue, k: usize) -> Result<JsValue, JsValue> {
+    utils::set_panic_hook();
+
+    let vectors: Vec<Vec<f64>> = serde_wasm_bindgen::from_value(vectors_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vectors: {}", e)))?;
+
+    if k == 0 {
+        return Err(JsValue::from_str("k must be positive."));
+    }
+
+    let magnitudes: Vec<f64> = vectors.iter().map(|v| magnitude(v)).collect();
+    let n = vectors.len();
+    let entries = (0..n)
+        .flat_map(|i| ((i + 1)..n).map(move |j| (i, j)))
+        .filter(|&(i, j)| vectors[i].len() == vectors[j].len())
+        .map(|(i, j)| HeapEntry {
+            score: cosine_with_magnitude(&vectors[i], magnitudes[i], &vectors[j]),
+            key: (i, j),
+            index: i * n + j,
+        });
+
+    let pairs: Vec<SimilarPair> = top_k_entries(entries, k.min(n * n.saturating_sub(1) / 2))
+        .into_iter()
+        .map(|e| SimilarPair { i: e.key.0, j: e.key.1, similarity: e.score })
+        .collect();
+
+    Ok(serde_wasm_bindgen::to_value(&pairs)?)
+}
+
+/// Pairwise distance matrix for `"euclidean"`, `"manhattan"` or
+/// `"cosine_distance"` (`1 - cosine`), in the `FlatMatrix` layout. Only the
+/// upper triangle is computed and mirrored; the diagonal is always 0.
+#[wasm_bindgen]
+pub fn distance_matrix(vectors_js: JsValue, metric: &str) -> Result<FlatMatrix, JsValue> {
+    utils::set_panic_hook();
+
+    let vectors: Vec<Vec<f64>> = serde_wasm_bindgen::from_value(vectors_js)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize vectors: {}", e)))?;
+
+    let (base, cosine_distance) = match metric {
+        "euclidean" => (Metric::Euclidean, false),
+        "manhattan" => (Metric::Manhattan, false),
+        "cosine_distance" => (Metric::Cosine, true),
+        _ => return Err(JsValue::from_str(&format!("Unknown metric: {}", metric))),
+    };
+
+    let n = vectors.len();
+    if let Some(first) = vectors.first() {
+        let dim = first.len();
+        if dim == 0 {
+            return Err(JsValue::from_str("Vectors cannot be empty."));
+        }
+        if let Some(i) = vectors.iter().position(|v| v.len() != dim) {
+            return Err(JsValue::from_str(&format!(
+                "Vector {} dimension mismatch: expected {}, got {}",
+                i,
+                dim,
+                vectors[i].len()
+            )));
+        }
+    }
+
+    let mut data = vec![0.0; n * n];
+
+    for i in 0..n {
+        for j in (i + 1)..n {
+            let value = base.compute(&vectors[i], &vectors[j]);
+            let distance = if cosine_distance { 1.0 - value } else { value };
+            data[i * n + j] = distance;
+            data[j * n + i] = distance;
+        }
+    }
+
+    Ok(FlatMatrix { data, n })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cosine_variants_agree() {
+        let vectors = vec![
+            vec![0.1, 0.7, -0.2],
+            vec![0.3, -0.4, 0.9],
+            vec![0.0, 0.0, 0.0],
+            vec![1e-8, 2.5, 3.0],
+        ];
+
+        let matrix = similarity_matrix(&vectors);
+        for (i, target) in vectors.iter().enumerate() {
+            let one_to_many = cosine_one_to_many(target, &vectors);
+            let bulk = cosine_bulk(&vec![target.clone(); vectors.len()], &vectors);
+            for (j, candidate) in vectors.iter().enumerate() {
+                let pairwise = cosine(target, candidate);
+                assert_eq!(pairwise.to_bits(), one_to_many[j].to_bits(), "one_to_many ({}, {})", i, j);
+                assert_eq!(pairwise.to_bits(), bulk[j].to_bits(), "bulk ({}, {})", i, j);
+                assert_eq!(pairwise.to_bits(), matrix[i][j].to_bits(), "matrix ({}, {})", i, j);
+            }
+        }
+    }
+
+    #[test]
+    fn cosine_is_clamped_to_unit_range() {
+        // sqrt(3)^2 rounds to 2.9999999999999996, so the raw cosine of [1, 1, 1]
+        // with itself is 1.0000000000000002
+        let v = vec![1.0, 1.0, 1.0];
+        let raw = simd::dot(&v, &v) / (magnitude(&v) * magnitude(&v));
+        assert!(raw > 1.0);
+
+        assert_eq!(cosine(&v, &v), 1.0);
+        let pool = std::slice::from_ref(&v);
+        assert_eq!(cosine_one_to_many(&v, pool), vec![1.0]);
+        assert_eq!(cosine_bulk(pool, pool), vec![1.0]);
+        assert_eq!(similarity_matrix(pool), vec![vec![1.0]]);
+
+        let negated: Vec<f64> = v.iter().map(|x| -x).collect();
+        assert_eq!(cosine(&v, &negated), -1.0);
+
+        assert_eq!(cosine_from_parts(1.0000000002, 1.0, 1.0), 1.0);
+        assert_eq!(cosine_from_parts(-1.0000000002, 1.0, 1.0), -1.0);
+    }
+
+    fn assert_same_model(a: &BanditModel, b: &BanditModel) {
+        assert_eq!(a.dimension, b.dimension);
+        assert_eq!(a.a_inv, b.a_inv);
+        assert_eq!(a.b, b.b);
+        assert_eq!(a.impressions, b.impressions);
+        assert_eq!(a.clicks, b.clicks);
+        assert_eq!(a.update_count, b.update_count);
+    }
+
+    #[test]
+    fn binary_round_trips_default_model() {
+        let model = initial_model(3, 2.0).unwrap();
+        let decoded = decode_model_binary(&encode_model_binary(&model).unwrap()).unwrap();
+
+        assert_same_model(&model, &decoded);
+        assert_eq!(decoded.impressions, None);
+        assert_eq!(decoded.clicks, None);
+        assert_eq!(decoded.update_count, Some(0));
+    }
+
+    #[test]
+    fn binary_round_trips_each_counter_combination() {
+        for mask in 0..8u8 {
+            let mut model = initial_model(2, 1.0).unwrap();
+            model.b = vec![0.25, -1.5];
+            model.impressions = (mask & 1 != 0).then_some(40);
+            model.clicks = (mask & 2 != 0).then_some(7);
+            model.update_count = (mask & 4 != 0).then_some(12);
+
+            let decoded = decode_model_binary(&encode_model_binary(&model).unwrap()).unwrap();
+            assert_same_model(&model, &decoded);
+        }
+    }
+
+    #[test]
+    fn sparse_entries_merge_repeated_indices() {
+        let entries = merge_sparse_entries(&[3, 1, 3, 0], &[1.0, 2.0, 2.0, -1.0]);
+        assert_eq!(entries, vec![(0, -1.0), (1, 2.0), (3, 3.0)]);
+    }
+
+    #[test]
+    fn ucb_of_no_articles_is_empty() {
+        let model = initial_model(4, 1.0).unwrap();
+        let results = compute_ucb_detailed(&model, Vec::new(), 0.1, &UcbOptions::default()).unwrap();
+        assert!(results.is_empty());
+
+        // Not validated when there is nothing to score
+        let zero_dim = BanditModel { dimension: 0, a_inv: Vec::new(), b: Vec::new(), ..initial_model(1, 1.0).unwrap() };
+        let results = compute_ucb_detailed(&zero_dim, Vec::new(), 0.1, &UcbOptions::default()).unwrap();
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn ucb_of_fresh_model_is_pure_exploration() {
+        let model = initial_model(2, 1.0).unwrap();
+        let articles = vec![Article { article_id: "a".to_string(), embedding: vec![1.0, 0.0] }];
+
+        // alpha = 0.5 + (1 - 0) * 0.5 = 1, x^T A_inv x = 1
+        let results = compute_ucb_detailed(&model, articles, 0.0, &UcbOptions::default()).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].exploitation, 0.0);
+        assert_eq!(results[0].exploration, 1.0);
+        assert_eq!(results[0].ucb, 1.0);
+    }
+
+    #[test]
+    fn non_finite_reward_is_rejected_without_touching_the_model() {
+        let mut a_inv = Array2::<f64>::eye(3) * 0.5;
+        let mut b = Array1::from(vec![0.1, -0.2, 0.3]);
+        let x = Array1::from(vec![1.0, 2.0, -1.0]);
+
+        for reward in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let result = sherman_morrison_update(&mut a_inv, &mut b, x.view(), reward);
+            assert_eq!(result, Err("Reward is non-finite (NaN/Inf).".to_string()));
+            assert_eq!(a_inv, Array2::<f64>::eye(3) * 0.5);
+            assert_eq!(b, Array1::from(vec![0.1, -0.2, 0.3]));
+        }
+    }
+
+    #[test]
+    fn compensated_sum_survives_cancellation() {
+        let values = [1e16, 1.0, -1e16, 1.0, 1e16, -1e16];
+
+        let naive: f64 = values.iter().sum();
+        assert_eq!(naive, 0.0);
+
+        let mut compensated = CompensatedSum::default();
+        for &v in &values {
+            compensated.add(v);
+        }
+        assert_eq!(compensated.total(), 2.0);
+    }
+
+    #[test]
+    fn canonical_json_round_trips_and_is_stable() {
+        let mut model = initial_model(3, 3.0).unwrap();
+        model.b = vec![0.1, -1.0 / 3.0, 1e-300];
+        model.a_inv[1] = 2.5e17;
+        model.impressions = Some(20);
+        model.clicks = Some(3);
+        model.shared = Some(SharedComponent {
+            dimension: 1,
+            a0_inv: vec![0.5],
+            b0: vec![-0.25],
+            b_cross: vec![0.1, 0.2, 0.3],
+        });
+
+        let json = canonical_model_json(&model).unwrap();
+        let parsed: BanditModel = serde_json::from_str(&json).unwrap();
+
+        assert_same_model(&model, &parsed);
+        let shared = parsed.shared.as_ref().unwrap();
+        assert_eq!((shared.dimension, &shared.a0_inv, &shared.b0), (1, &vec![0.5], &vec![-0.25]));
+        assert_eq!(shared.b_cross, vec![0.1, 0.2, 0.3]);
+        assert_eq!(canonical_model_json(&parsed).unwrap(), json);
+    }
+
+    #[test]
+    fn sorted_ucb_is_non_increasing_with_ties_by_id_and_nan_last() {
+        let result = |id: &str, ucb: f64| UcbResult { article_id: id.to_string(), ucb };
+        let mut results = vec![
+            result("d", 0.5),
+            result("nan-neg", -f64::NAN),
+            result("b", 2.0),
+            result("c", 0.5),
+            result("nan", f64::NAN),
+            result("a", 0.5),
+            result("e", f64::NEG_INFINITY),
+        ];
+
+        sort_ucb_results(&mut results);
+
+        let ids: Vec<&str> = results.iter().map(|r| r.article_id.as_str()).collect();
+        assert_eq!(ids, vec!["b", "a", "c", "d", "e", "nan", "nan-neg"]);
+        let scores: Vec<f64> = results.iter().map(|r| r.ucb).filter(|u| !u.is_nan()).collect();
+        assert!(scores.windows(2).all(|w| w[0] >= w[1]));
+    }
+
+    #[test]
+    fn blended_counters_follow_the_weight() {
+        assert_eq!(blend_counter(Some(100), Some(20), 0.25), Some(40));
+        assert_eq!(blend_counter(Some(10), None, 0.5), Some(5));
+        assert_eq!(blend_counter(None, None, 0.5), None);
+    }
+
+    #[test]
+    fn top_k_with_huge_k_returns_everything() {
+        let entries = (0..5usize).map(|index| HeapEntry { score: index as f64, key: index, index });
+        let top: Vec<usize> = top_k_entries(entries, usize::MAX).into_iter().map(|e| e.index).collect();
+        assert_eq!(top, vec![4, 3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn top_k_ranks_nan_last() {
+        let scores = [f64::NAN, 1.0, -f64::NAN, 3.0, f64::NEG_INFINITY];
+        let keys = ["a", "b", "c", "d", "e"];
+
+        assert_eq!(top_k_indices(&scores, &keys, 2), vec![3, 1]);
+        assert_eq!(top_k_indices(&scores, &keys, 5), vec![3, 1, 4, 0, 2]);
+    }
 }